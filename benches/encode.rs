@@ -0,0 +1,122 @@
+//Times encode_to_slice() against the previous 3-bytes-per-iteration loop,
+//and encode_to_slice_double() with and without building its table per call.
+//Run with `cargo bench`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use base64::{encode_size, encode_to_slice, encode_to_slice_double, Alphabet, DoubleSymbolTable};
+
+//encode_to_slice() as it was before reading whole u64 words, copied verbatim
+const BASE64_TABLE: [u8; 64] = [
+    b'A', b'B', b'C', b'D', b'E', b'F', b'G', b'H', b'I', b'J', b'K', b'L', b'M', b'N', b'O', b'P',
+    b'Q', b'R', b'S', b'T', b'U', b'V', b'W', b'X', b'Y', b'Z', b'a', b'b', b'c', b'd', b'e', b'f',
+    b'g', b'h', b'i', b'j', b'k', b'l', b'm', b'n', b'o', b'p', b'q', b'r', b's', b't', b'u', b'v',
+    b'w', b'x', b'y', b'z', b'0', b'1', b'2', b'3', b'4', b'5', b'6', b'7', b'8', b'9', b'+', b'/',
+];
+
+fn read_u32(s: &[u8]) -> u32 {
+    let temp = [0, s[0], s[1], s[2] ];
+    u32::from_be_bytes( temp)
+}
+
+#[allow(clippy::identity_op)]
+fn encode_by_chunk(input: &[u8], output: &mut [u8]) -> usize {
+    let mut input_index: usize = 0;
+    let mut output_index: usize = 0;
+
+    const LOW_SIX_BITS: u32 = 0x3f;
+    const LOW_SIX_BITS_U8: u8 = 0x3f;
+
+    let rem = input.len() % 3;
+    let last_index = input.len() - rem;
+
+    while input_index < last_index {
+
+        //read 3 bytes into u32
+        let input_chunk = read_u32(&input[input_index..(input_index + 3) ]);
+        let output_chunk = &mut output[output_index..(output_index + 4) ];
+
+        output_chunk[0] = BASE64_TABLE[ ( (input_chunk >> 18) & LOW_SIX_BITS) as  usize];
+        output_chunk[1] = BASE64_TABLE[ ( (input_chunk >> 12) & LOW_SIX_BITS) as  usize];
+        output_chunk[2] = BASE64_TABLE[ ( (input_chunk >> 6) & LOW_SIX_BITS) as  usize];
+        output_chunk[3] = BASE64_TABLE[ ( (input_chunk >> 0) & LOW_SIX_BITS) as  usize];
+
+        input_index += 3;
+        output_index += 4;
+    }
+
+    if rem == 2 {
+        let output_chunk = &mut output[output_index..(output_index + 3)];
+
+        output_chunk[0] = BASE64_TABLE[ ((input[last_index] >> 2) & LOW_SIX_BITS_U8) as usize];
+        output_chunk[1] = BASE64_TABLE[ (((input[last_index] << 4) 
+                | (input[last_index + 1] >> 4) ) 
+                & LOW_SIX_BITS_U8) as usize];
+        output_chunk[2] = BASE64_TABLE[ ((input[last_index + 1] << 2) & LOW_SIX_BITS_U8) as usize];
+
+        output_index += 3;
+    } else if rem == 1 {
+        let output_chunk = &mut output[output_index..(output_index + 2)];
+
+        output_chunk[0] = BASE64_TABLE[ ((input[last_index] >> 2) & LOW_SIX_BITS_U8) as usize];
+        output_chunk[1] = BASE64_TABLE[ ((input[last_index] << 4) & LOW_SIX_BITS_U8) as usize];
+
+        output_index += 2;
+    }
+
+    output_index
+}
+
+//runs f repeatedly for about 200ms and returns the throughput in MB/s
+fn throughput<F: FnMut()>(len: usize, mut f: F) -> f64 {
+    let mut iterations = 0u64;
+    let start = Instant::now();
+
+    while start.elapsed() < Duration::from_millis(200) {
+        f();
+        iterations += 1;
+    }
+
+    (len as f64 * iterations as f64) / start.elapsed().as_secs_f64() / 1e6
+}
+
+fn main() {
+    let double_table = DoubleSymbolTable::new(&Alphabet::STANDARD);
+
+    println!(
+        "{:>10} {:>14} {:>14} {:>14} {:>14}",
+        "bytes", "by chunk MB/s", "wide MB/s", "double MB/s", "+table MB/s"
+    );
+
+    for &len in &[12, 100, 1024, 32 * 1024, 1024 * 1024] {
+        let input: Vec<u8> = (0..len).map(|i| (i * 31 + i / 256) as u8).collect();
+        let mut expected = vec![0u8; encode_size(len, false)];
+        let mut output = vec![0u8; encode_size(len, false)];
+
+        encode_by_chunk(&input, &mut expected);
+        encode_to_slice(&input, &mut output, &Alphabet::STANDARD);
+        assert_eq!(expected, output, "output differs for {} bytes", len);
+
+        let by_chunk = throughput(len, || {
+            black_box(encode_by_chunk(black_box(&input), &mut expected));
+        });
+        let wide = throughput(len, || {
+            black_box(encode_to_slice(black_box(&input), &mut output, &Alphabet::STANDARD));
+        });
+
+        let double = throughput(len, || {
+            black_box(encode_to_slice_double(black_box(&input), &mut output, &double_table));
+        });
+        // including building the table for every call
+        let with_table = throughput(len, || {
+            let table = DoubleSymbolTable::new(black_box(&Alphabet::STANDARD));
+            black_box(encode_to_slice_double(black_box(&input), &mut output, &table));
+        });
+
+        println!(
+            "{:>10} {:>14.0} {:>14.0} {:>14.0} {:>14.0}",
+            len, by_chunk, wide, double, with_table
+        );
+    }
+}
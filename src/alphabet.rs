@@ -0,0 +1,180 @@
+use core::fmt;
+
+use crate::encode::PAD_BYTE;
+
+pub(crate) const INVALID_VALUE: u8 = 0xff;
+
+//The 64 symbols used to encode 6-bit values, together with the
+//reverse table used to decode them.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Alphabet {
+    pub(crate) encode_table: [u8; 64],
+    //maps every byte to its 6-bit value or INVALID_VALUE
+    pub(crate) decode_table: [u8; 256],
+}
+
+impl Alphabet {
+    //RFC 4648 standard alphabet, using '+' and '/'
+    pub const STANDARD: Alphabet =
+        Alphabet::from_table(b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/");
+
+    //RFC 4648 URL and filename safe alphabet, using '-' and '_'
+    pub const URL_SAFE: Alphabet =
+        Alphabet::from_table(b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_");
+
+    //Builds an alphabet from 64 distinct ASCII symbols, in order of their value.
+    //Being a const fn, a custom alphabet can be checked at compile time:
+    //
+    //const CUSTOM: Alphabet = match Alphabet::new("...") {
+    //    Ok(alphabet) => alphabet,
+    //    Err(_) => panic!("invalid alphabet"),
+    //};
+    pub const fn new(symbols: &str) -> Result<Alphabet, ParseAlphabetError> {
+        let symbols = symbols.as_bytes();
+
+        if symbols.len() != 64 {
+            return Err(ParseAlphabetError::InvalidLength(symbols.len()));
+        }
+
+        let mut encode_table = [0u8; 64];
+        let mut i = 0;
+
+        while i < 64 {
+            encode_table[i] = symbols[i];
+            i += 1;
+        }
+
+        Alphabet::validate(&encode_table)
+    }
+
+    //only used for the built-in alphabets, which are known to be valid
+    const fn from_table(encode_table: &[u8; 64]) -> Alphabet {
+        match Alphabet::validate(encode_table) {
+            Ok(alphabet) => alphabet,
+            Err(_) => panic!("invalid built-in alphabet"),
+        }
+    }
+
+    //checks every symbol while building the decode table
+    const fn validate(encode_table: &[u8; 64]) -> Result<Alphabet, ParseAlphabetError> {
+        let mut decode_table = [INVALID_VALUE; 256];
+        let mut i = 0;
+
+        while i < 64 {
+            let byte = encode_table[i];
+
+            if !byte.is_ascii() {
+                return Err(ParseAlphabetError::NonAsciiByte(byte));
+            }
+            if byte == PAD_BYTE {
+                return Err(ParseAlphabetError::ReservedByte(byte));
+            }
+            if decode_table[byte as usize] != INVALID_VALUE {
+                return Err(ParseAlphabetError::DuplicatedByte(byte));
+            }
+
+            decode_table[byte as usize] = i as u8;
+            i += 1;
+        }
+
+        Ok(Alphabet {
+            encode_table: *encode_table,
+            decode_table,
+        })
+    }
+
+    //the 64 symbols in order of their value
+    pub fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.encode_table).expect("Invalid UTF8")
+    }
+}
+
+impl fmt::Debug for Alphabet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Alphabet").field(&self.as_str()).finish()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseAlphabetError {
+    //alphabet does not have exactly 64 symbols, holds the actual length
+    InvalidLength(usize),
+    //symbol appears more than once
+    DuplicatedByte(u8),
+    //symbol is not ASCII
+    NonAsciiByte(u8),
+    //symbol collides with PAD_BYTE
+    ReservedByte(u8),
+}
+
+impl fmt::Display for ParseAlphabetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            ParseAlphabetError::InvalidLength(len) => {
+                write!(f, "Invalid alphabet length {}, expected 64.", len)
+            }
+            ParseAlphabetError::DuplicatedByte(byte) => {
+                write!(f, "Duplicated symbol '{}'.", byte as char)
+            }
+            ParseAlphabetError::NonAsciiByte(byte) => write!(f, "Non-ASCII byte 0x{:02x}.", byte),
+            ParseAlphabetError::ReservedByte(byte) => {
+                write!(f, "Symbol '{}' is reserved for padding.", byte as char)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseAlphabetError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_decode_table() {
+        for alphabet in &[Alphabet::STANDARD, Alphabet::URL_SAFE] {
+            for (value, &symbol) in alphabet.encode_table.iter().enumerate() {
+                assert_eq!(value as u8, alphabet.decode_table[symbol as usize]);
+            }
+
+            let valid = alphabet.decode_table.iter().filter(|&&v| v != INVALID_VALUE);
+            assert_eq!(64, valid.count());
+        }
+    }
+
+    #[test]
+    fn check_custom_alphabet() {
+        const CRYPT: Alphabet = match Alphabet::new(
+            "./0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz",
+        ) {
+            Ok(alphabet) => alphabet,
+            Err(_) => panic!("invalid alphabet"),
+        };
+
+        assert_eq!(b'.', CRYPT.encode_table[0]);
+        assert_eq!(63, CRYPT.decode_table[b'z' as usize]);
+        assert_eq!(Ok(Alphabet::STANDARD), Alphabet::new(Alphabet::STANDARD.as_str()));
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn check_invalid_alphabets() {
+        let standard = Alphabet::STANDARD.as_str();
+
+        assert_eq!(Err(ParseAlphabetError::InvalidLength(63)), Alphabet::new(&standard[1..]));
+        assert_eq!(
+            Err(ParseAlphabetError::DuplicatedByte(b'A')),
+            Alphabet::new(&standard.replace('B', "A"))
+        );
+        assert_eq!(
+            Err(ParseAlphabetError::ReservedByte(b'=')),
+            Alphabet::new(&standard.replace('/', "="))
+        );
+        // 'é' is two bytes, so drop two symbols to keep the length at 64 bytes
+        assert_eq!(
+            Err(ParseAlphabetError::NonAsciiByte(0xc3)),
+            Alphabet::new(&standard[2..].replace('C', "Cé"))
+        );
+    }
+}
@@ -0,0 +1,321 @@
+use core::arch::x86_64::*;
+use std::is_x86_feature_detected;
+
+use crate::alphabet::Alphabet;
+
+//input bytes encoded per iteration, giving 32 symbols
+const ENCODE_BLOCK_LEN: usize = 24;
+//each iteration loads 16 bytes at offset 12, reading 4 bytes past the block
+const ENCODE_LOAD_LEN: usize = 28;
+//symbols decoded per iteration, giving 24 bytes
+const DECODE_BLOCK_LEN: usize = 32;
+
+//Encodes as many 24-byte blocks of input as possible with AVX2, if the CPU
+//supports it and the alphabet is laid out like the standard one.
+//The rest is left for the scalar loop.
+//Returns the number of bytes read from input and written to output.
+pub(crate) fn encode_prefix(input: &[u8], output: &mut [u8], alphabet: &Alphabet) -> (usize, usize) {
+    if input.len() < ENCODE_LOAD_LEN
+        || !is_x86_feature_detected!("avx2")
+        || !has_standard_layout(alphabet)
+    {
+        return (0, 0);
+    }
+
+    let table = &alphabet.encode_table;
+
+    // SAFETY: avx2 support was just checked
+    unsafe { encode_blocks(input, output, table[62], table[63]) }
+}
+
+//Decodes 32-symbol blocks from the start of input with AVX2, if the CPU
+//supports it and the alphabet is laid out like the standard one.
+//Stops at the first block holding a byte outside the alphabet, so that the
+//scalar loop can report it with its exact offset.
+//Returns the number of bytes read from input and written to output.
+pub(crate) fn decode_prefix(input: &[u8], output: &mut [u8], alphabet: &Alphabet) -> (usize, usize) {
+    if input.len() < DECODE_BLOCK_LEN
+        || !is_x86_feature_detected!("avx2")
+        || !has_standard_layout(alphabet)
+    {
+        return (0, 0);
+    }
+
+    let table = &alphabet.encode_table;
+
+    // SAFETY: avx2 support was just checked
+    unsafe { decode_blocks(input, output, table[62], table[63]) }
+}
+
+//the vectorised translation computes 'A'-'Z', 'a'-'z' and '0'-'9' arithmetically,
+//so only the last two symbols may differ from the standard alphabet
+fn has_standard_layout(alphabet: &Alphabet) -> bool {
+    alphabet.encode_table[..62] == Alphabet::STANDARD.encode_table[..62]
+}
+
+//Based on Wojciech Muła's SIMD base64 encoding: both 128-bit lanes take
+//12 input bytes and turn them into 16 symbols.
+#[target_feature(enable = "avx2")]
+unsafe fn encode_blocks(
+    input: &[u8],
+    output: &mut [u8],
+    symbol_62: u8,
+    symbol_63: u8,
+) -> (usize, usize) {
+    let mut input_index: usize = 0;
+    let mut output_index: usize = 0;
+
+    // spreads 3 bytes over 4 bytes per u32, as b1 b0 b2 b1 (little-endian)
+    let shuffle = _mm256_setr_epi8(
+        1, 0, 2, 1, 4, 3, 5, 4, 7, 6, 8, 7, 10, 9, 11, 10, //
+        1, 0, 2, 1, 4, 3, 5, 4, 7, 6, 8, 7, 10, 9, 11, 10,
+    );
+
+    // offset added to each 6-bit value, indexed as computed in lookup()
+    let shift_lut = shift_lut(symbol_62, symbol_63);
+
+    while input.len() - input_index >= ENCODE_LOAD_LEN {
+        let lo = &input[input_index..(input_index + 16)];
+        let hi = &input[(input_index + 12)..(input_index + 28)];
+        let out = &mut output[output_index..(output_index + 32)];
+
+        let lo = _mm_loadu_si128(lo.as_ptr() as *const __m128i);
+        let hi = _mm_loadu_si128(hi.as_ptr() as *const __m128i);
+        let block = _mm256_inserti128_si256(_mm256_castsi128_si256(lo), hi, 1);
+
+        let indices = split_sextets(_mm256_shuffle_epi8(block, shuffle));
+        let symbols = lookup(indices, shift_lut);
+
+        _mm256_storeu_si256(out.as_mut_ptr() as *mut __m256i, symbols);
+
+        input_index += ENCODE_BLOCK_LEN;
+        output_index += 32;
+    }
+
+    (input_index, output_index)
+}
+
+//moves the four 6-bit values of every u32 into their own byte
+#[target_feature(enable = "avx2")]
+unsafe fn split_sextets(block: __m256i) -> __m256i {
+    let t0 = _mm256_and_si256(block, _mm256_set1_epi32(0x0fc0fc00));
+    let t1 = _mm256_mulhi_epu16(t0, _mm256_set1_epi32(0x04000040));
+    let t2 = _mm256_and_si256(block, _mm256_set1_epi32(0x003f03f0));
+    let t3 = _mm256_mullo_epi16(t2, _mm256_set1_epi32(0x01000010));
+
+    _mm256_or_si256(t1, t3)
+}
+
+//turns 6-bit values into symbols by adding the offset of their range:
+//0..=25 -> 13, 26..=51 -> 0, 52..=61 -> 1..=10, 62 -> 11, 63 -> 12
+#[target_feature(enable = "avx2")]
+unsafe fn lookup(indices: __m256i, shift_lut: __m256i) -> __m256i {
+    let mut reduced = _mm256_subs_epu8(indices, _mm256_set1_epi8(51));
+    let less = _mm256_cmpgt_epi8(_mm256_set1_epi8(26), indices);
+    reduced = _mm256_or_si256(reduced, _mm256_and_si256(less, _mm256_set1_epi8(13)));
+
+    _mm256_add_epi8(_mm256_shuffle_epi8(shift_lut, reduced), indices)
+}
+
+#[target_feature(enable = "avx2")]
+unsafe fn shift_lut(symbol_62: u8, symbol_63: u8) -> __m256i {
+    let offset = |symbol: u8, value: u8| symbol.wrapping_sub(value) as i8;
+
+    let lower = offset(b'a', 26);
+    let digit = offset(b'0', 52);
+    let s62 = offset(symbol_62, 62);
+    let s63 = offset(symbol_63, 63);
+    let upper = b'A' as i8;
+
+    _mm256_setr_epi8(
+        lower, digit, digit, digit, digit, digit, digit, digit, digit, digit, digit, s62, s63,
+        upper, 0, 0, //
+        lower, digit, digit, digit, digit, digit, digit, digit, digit, digit, digit, s62, s63,
+        upper, 0, 0,
+    )
+}
+
+#[target_feature(enable = "avx2")]
+unsafe fn decode_blocks(
+    input: &[u8],
+    output: &mut [u8],
+    symbol_62: u8,
+    symbol_63: u8,
+) -> (usize, usize) {
+    let mut input_index: usize = 0;
+    let mut output_index: usize = 0;
+
+    // moves the 3 bytes of every u32, big-endian, to the front of each lane
+    let shuffle = _mm256_setr_epi8(
+        2, 1, 0, 6, 5, 4, 10, 9, 8, 14, 13, 12, -1, -1, -1, -1, //
+        2, 1, 0, 6, 5, 4, 10, 9, 8, 14, 13, 12, -1, -1, -1, -1,
+    );
+    // then joins the 12 bytes of both lanes
+    let permute = _mm256_setr_epi32(0, 1, 2, 4, 5, 6, 7, 7);
+
+    while input.len() - input_index >= DECODE_BLOCK_LEN {
+        let block = &input[input_index..(input_index + DECODE_BLOCK_LEN)];
+        let block = _mm256_loadu_si256(block.as_ptr() as *const __m256i);
+
+        let values = match translate(block, symbol_62, symbol_63) {
+            Some(values) => values,
+            None => break,
+        };
+
+        // a b c d -> ab cd -> abcd, 6 bits each
+        let pairs = _mm256_maddubs_epi16(values, _mm256_set1_epi32(0x01400140));
+        let words = _mm256_madd_epi16(pairs, _mm256_set1_epi32(0x00011000));
+        let bytes = _mm256_permutevar8x32_epi32(_mm256_shuffle_epi8(words, shuffle), permute);
+
+        let out = &mut output[output_index..(output_index + 24)];
+        _mm_storeu_si128(out.as_mut_ptr() as *mut __m128i, _mm256_castsi256_si128(bytes));
+        _mm_storel_epi64(
+            out[16..].as_mut_ptr() as *mut __m128i,
+            _mm256_extracti128_si256(bytes, 1),
+        );
+
+        input_index += DECODE_BLOCK_LEN;
+        output_index += 24;
+    }
+
+    (input_index, output_index)
+}
+
+//turns symbols into their 6-bit values by adding the offset of their range.
+//Returns None if any byte is not in the alphabet.
+#[target_feature(enable = "avx2")]
+unsafe fn translate(block: __m256i, symbol_62: u8, symbol_63: u8) -> Option<__m256i> {
+    // bytes from 0x80 compare as negative, so they fall in no range
+    let in_range = |first: u8, last: u8| {
+        _mm256_and_si256(
+            _mm256_cmpgt_epi8(block, _mm256_set1_epi8(first as i8 - 1)),
+            _mm256_cmpgt_epi8(_mm256_set1_epi8(last as i8 + 1), block),
+        )
+    };
+
+    let upper = in_range(b'A', b'Z');
+    let lower = in_range(b'a', b'z');
+    let digit = in_range(b'0', b'9');
+    let is_62 = _mm256_cmpeq_epi8(block, _mm256_set1_epi8(symbol_62 as i8));
+    let is_63 = _mm256_cmpeq_epi8(block, _mm256_set1_epi8(symbol_63 as i8));
+
+    let valid = _mm256_or_si256(
+        _mm256_or_si256(upper, lower),
+        _mm256_or_si256(digit, _mm256_or_si256(is_62, is_63)),
+    );
+    if _mm256_movemask_epi8(valid) != -1 {
+        return None;
+    }
+
+    let offset = |mask: __m256i, symbol: u8, value: u8| {
+        _mm256_and_si256(mask, _mm256_set1_epi8(value.wrapping_sub(symbol) as i8))
+    };
+
+    let offsets = _mm256_or_si256(
+        _mm256_or_si256(offset(upper, b'A', 0), offset(lower, b'a', 26)),
+        _mm256_or_si256(
+            offset(digit, b'0', 52),
+            _mm256_or_si256(offset(is_62, symbol_62, 62), offset(is_63, symbol_63, 63)),
+        ),
+    );
+
+    Some(_mm256_add_epi8(block, offsets))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::{decode_symbols, decode_symbols_runtime, decoded_size};
+    use crate::encode::{encode_size, encode_to_slice};
+    use crate::test_util::random_bytes;
+    use std::vec;
+
+    #[test]
+    fn check_same_as_scalar() {
+        if !is_x86_feature_detected!("avx2") {
+            return;
+        }
+
+        let mut seed = 0x2545f4914f6cdd1d;
+
+        for len in 0..600 {
+            let input = random_bytes(len, &mut seed);
+
+            for alphabet in &[Alphabet::STANDARD, Alphabet::URL_SAFE] {
+                let mut expected = vec![0u8; encode_size(len, false)];
+                let mut output = vec![0u8; encode_size(len, false)];
+
+                encode_to_slice(&input, &mut expected, alphabet);
+
+                let (read, written) = encode_prefix(&input, &mut output, alphabet);
+                encode_to_slice(&input[read..], &mut output[written..], alphabet);
+
+                assert_eq!(expected, output);
+                assert_eq!(len >= ENCODE_LOAD_LEN, read > 0);
+            }
+        }
+    }
+
+    #[test]
+    fn check_decode_same_as_scalar() {
+        if !is_x86_feature_detected!("avx2") {
+            return;
+        }
+
+        let mut seed = 0x9e3779b97f4a7c15;
+
+        for len in 0..600 {
+            let input = random_bytes(len, &mut seed);
+
+            for alphabet in &[Alphabet::STANDARD, Alphabet::URL_SAFE] {
+                let mut encoded = vec![0u8; encode_size(len, false)];
+                encode_to_slice(&input, &mut encoded, alphabet);
+
+                let mut output = vec![0u8; decoded_size(encoded.len())];
+                let (read, _) = decode_prefix(&encoded, &mut output, alphabet);
+                assert_eq!(encoded.len() >= DECODE_BLOCK_LEN, read > 0);
+
+                assert_eq!(
+                    Ok(len),
+                    decode_symbols_runtime(&encoded, &mut output, alphabet, false)
+                );
+                assert_eq!(input, output);
+            }
+        }
+    }
+
+    #[test]
+    fn check_decode_errors_same_as_scalar() {
+        let mut seed = 0xdeadbeefcafef00d;
+        let input = random_bytes(300, &mut seed);
+        let mut encoded = vec![0u8; encode_size(input.len(), false)];
+        encode_to_slice(&input, &mut encoded, &Alphabet::STANDARD);
+
+        let mut output = vec![0u8; decoded_size(encoded.len())];
+
+        for offset in 0..encoded.len() {
+            for &byte in &[b'*', b'=', b'-', 0x80, 0xff, b'@', b'[', b'`', b'{', b':'] {
+                let mut invalid = encoded.clone();
+                invalid[offset] = byte;
+
+                assert_eq!(
+                    decode_symbols(&invalid, &mut output, &Alphabet::STANDARD, false),
+                    decode_symbols_runtime(&invalid, &mut output, &Alphabet::STANDARD, false)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn check_custom_alphabet_falls_back() {
+        let reversed = Alphabet::new(
+            "/+9876543210zyxwvutsrqponmlkjihgfedcbaZYXWVUTSRQPONMLKJIHGFEDCBA",
+        )
+        .unwrap();
+        let input = [0u8; 100];
+        let mut output = [0u8; 136];
+
+        assert_eq!((0, 0), encode_prefix(&input, &mut output, &reversed));
+        assert_eq!((0, 0), decode_prefix(&output, &mut [0u8; 102], &reversed));
+    }
+}
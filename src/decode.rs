@@ -0,0 +1,610 @@
+use core::fmt;
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use crate::alphabet::{Alphabet, INVALID_VALUE};
+use crate::encode::PAD_BYTE;
+use crate::engine::{Config, DecodePaddingMode, Engine, STANDARD};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    //byte that is not part of the alphabet, with its offset in the input
+    InvalidByte(usize, u8),
+    //number of symbols can not come from any encoded input
+    InvalidLength,
+    //misplaced or excess PAD_BYTE at the given offset
+    InvalidPadding(usize),
+    //last symbol has non-zero bits that do not belong to any decoded byte
+    InvalidLastSymbol(usize, u8),
+}
+
+impl DecodeError {
+    //the same error for input that starts `by` bytes later in a longer input
+    pub(crate) fn shift_offset(self, by: usize) -> DecodeError {
+        match self {
+            DecodeError::InvalidByte(offset, byte) => DecodeError::InvalidByte(offset + by, byte),
+            DecodeError::InvalidLength => DecodeError::InvalidLength,
+            DecodeError::InvalidPadding(offset) => DecodeError::InvalidPadding(offset + by),
+            DecodeError::InvalidLastSymbol(offset, byte) => {
+                DecodeError::InvalidLastSymbol(offset + by, byte)
+            }
+        }
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            DecodeError::InvalidByte(offset, byte) => {
+                write!(f, "Invalid symbol {}, offset {}.", Symbol(byte), offset)
+            }
+            DecodeError::InvalidLength => write!(f, "Invalid input length."),
+            DecodeError::InvalidPadding(offset) => write!(f, "Invalid padding, offset {}.", offset),
+            DecodeError::InvalidLastSymbol(offset, byte) => write!(
+                f,
+                "Invalid last symbol {}, offset {}: trailing bits are not zero.",
+                Symbol(byte),
+                offset
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DecodeError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeSliceError {
+    //input is not valid Base64
+    Decode(DecodeError),
+    //output buffer can not hold the decoded input.
+    //needed is the decoded size, without the padding.
+    OutputTooSmall { needed: usize },
+}
+
+impl From<DecodeError> for DecodeSliceError {
+    fn from(e: DecodeError) -> DecodeSliceError {
+        DecodeSliceError::Decode(e)
+    }
+}
+
+impl fmt::Display for DecodeSliceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            DecodeSliceError::Decode(ref e) => e.fmt(f),
+            DecodeSliceError::OutputTooSmall { needed } => {
+                write!(f, "Output buffer too small, {} bytes needed.", needed)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DecodeSliceError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            DecodeSliceError::Decode(ref e) => Some(e),
+            DecodeSliceError::OutputTooSmall { .. } => None,
+        }
+    }
+}
+
+//shows a byte the way it appears in encoded text
+struct Symbol(u8);
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_ascii_graphic() {
+            write!(f, "'{}'", self.0 as char)
+        } else {
+            write!(f, "0x{:02x}", self.0)
+        }
+    }
+}
+
+//Decodes Base64 with the STANDARD engine into arbitrary octets.
+//Returns a Vec<u8>.
+#[cfg(feature = "alloc")]
+pub fn decode<T: AsRef<[u8]>>(input: T) -> Result<Vec<u8>, DecodeError> {
+    STANDARD.decode(input)
+}
+
+//Decodes Base64 with the STANDARD engine into the given buffer.
+//Returns the number of bytes written, or an error if input is invalid or
+//output is too small.
+pub fn decode_slice<T: AsRef<[u8]>>(
+    input: T,
+    output: &mut [u8],
+) -> Result<usize, DecodeSliceError> {
+    STANDARD.decode_slice(input, output)
+}
+
+//Decodes Base64 with the STANDARD engine over the start of buf itself, e.g.
+//to decode a received Vec<u8> without a second allocation:
+//
+//let len = base64::decode_in_place(&mut received)?.len();
+//received.truncate(len);
+//
+//Returns the decoded part of buf; on error the contents of buf are unspecified.
+pub fn decode_in_place(buf: &mut [u8]) -> Result<&mut [u8], DecodeError> {
+    STANDARD.decode_in_place(buf)
+}
+
+//this helper function combines the engine's internal_decode() and check_padding().
+//output must be at least decoded_size(input.len()) long.
+pub(crate) fn decode_with_padding<E: Engine + ?Sized>(
+    input: &[u8],
+    output: &mut [u8],
+    engine: &E,
+) -> Result<usize, DecodeError> {
+    let pad_len = trailing_pad_len(input);
+    let symbols_len = input.len() - pad_len;
+
+    //symbols come first so that a pad among them is reported at its own offset
+    let decoded_bytes = engine.internal_decode(&input[..symbols_len], output)?;
+    check_padding(symbols_len, pad_len, engine.config().decode_padding_mode())?;
+
+    Ok(decoded_bytes)
+}
+
+//symbols decode_in_place() copies out of the buffer at a time, a multiple of 4
+const IN_PLACE_BLOCK_LEN: usize = 1024;
+
+//decode_with_padding() writing the decoded bytes over the start of buf.
+//the output never overtakes the input, as 4 symbols make at most 3 bytes,
+//so each block is copied out and decoded back into buf.
+//Returns the decoded part of buf; on error the contents of buf are unspecified.
+pub(crate) fn decode_in_place_with_padding<'a, E: Engine + ?Sized>(
+    buf: &'a mut [u8],
+    engine: &E,
+) -> Result<&'a mut [u8], DecodeError> {
+    let pad_len = trailing_pad_len(buf);
+    let symbols_len = buf.len() - pad_len;
+    let chunks_len = complete_chunks_len(buf);
+
+    let mut block = [0u8; IN_PLACE_BLOCK_LEN];
+    let mut read = 0;
+    let mut written = 0;
+
+    while read < symbols_len {
+        //the last chunk is decoded on its own, like decode_with_padding() does
+        let len = if read < chunks_len {
+            (chunks_len - read).min(IN_PLACE_BLOCK_LEN)
+        } else {
+            symbols_len - read
+        };
+
+        block[..len].copy_from_slice(&buf[read..(read + len)]);
+        written += engine
+            .internal_decode(&block[..len], &mut buf[written..])
+            .map_err(|e| e.shift_offset(read))?;
+        read += len;
+    }
+
+    check_padding(symbols_len, pad_len, engine.config().decode_padding_mode())?;
+
+    Ok(&mut buf[..written])
+}
+
+//const counterpart of decode_with_padding(), for a GeneralPurpose engine's config
+pub(crate) const fn decode_with_config(
+    input: &[u8],
+    output: &mut [u8],
+    config: &Config,
+) -> Result<usize, DecodeError> {
+    let pad_len = trailing_pad_len(input);
+    let symbols_len = input.len() - pad_len;
+    let symbols = input.split_at(symbols_len).0;
+
+    let decoded_bytes = match decode_symbols(
+        symbols,
+        output,
+        config.alphabet(),
+        config.decode_allow_trailing_bits(),
+    ) {
+        Ok(len) => len,
+        Err(e) => return Err(e),
+    };
+
+    match check_padding(symbols_len, pad_len, config.decode_padding_mode()) {
+        Ok(()) => Ok(decoded_bytes),
+        Err(e) => Err(e),
+    }
+}
+
+//number of PAD_BYTEs at the end of input
+const fn trailing_pad_len(input: &[u8]) -> usize {
+    let mut len = 0;
+
+    while len < input.len() && input[input.len() - 1 - len] == PAD_BYTE {
+        len += 1;
+    }
+
+    len
+}
+
+//for a stream whose input so far is `input`: the number of leading symbols
+//that can be decoded before the rest of the stream is known.
+//these are the complete chunks followed by at least one symbol that is not a
+//pad, as the last chunk has to be checked together with its padding.
+fn complete_chunks_len(input: &[u8]) -> usize {
+    let symbols_len = input.len() - trailing_pad_len(input);
+
+    symbols_len.saturating_sub(1) / 4 * 4
+}
+
+//the decode step of the streaming decoders, for the symbols buffered so far:
+//decodes the chunks known not to be the last one, or all of input once the
+//stream has ended.
+//a full buffer without such a chunk holds nothing but pads, which can not be
+//valid, so it is decoded as the end of the stream to report the error.
+//errors count offsets from input_offset, the offset of input in the stream.
+//Returns the number of symbols decoded and of bytes written.
+#[cfg(feature = "std")]
+pub(crate) fn decode_buffered<E: Engine + ?Sized>(
+    input: &[u8],
+    output: &mut [u8],
+    input_offset: usize,
+    buffer_full: bool,
+    stream_ended: bool,
+    engine: &E,
+) -> Result<(usize, usize), DecodeError> {
+    let chunks_len = complete_chunks_len(input);
+
+    let decoded = if stream_ended || (buffer_full && chunks_len == 0) {
+        decode_with_padding(input, output, engine).map(|len| (input.len(), len))
+    } else {
+        engine
+            .internal_decode(&input[..chunks_len], output)
+            .map(|len| (chunks_len, len))
+    };
+
+    decoded.map_err(|e| e.shift_offset(input_offset))
+}
+
+//validates the pad_len trailing PAD_BYTEs that follow symbols_len symbols.
+//errors hold the offset of the first pad that is out of place.
+pub(crate) const fn check_padding(
+    symbols_len: usize,
+    pad_len: usize,
+    mode: DecodePaddingMode,
+) -> Result<(), DecodeError> {
+    if symbols_len % 4 == 1 {
+        return Err(DecodeError::InvalidLength);
+    }
+
+    let expected_pad_len = (4 - symbols_len % 4) % 4;
+
+    match mode {
+        DecodePaddingMode::Indifferent if pad_len == 0 => Ok(()),
+        DecodePaddingMode::RequireNone if pad_len == 0 => Ok(()),
+        DecodePaddingMode::RequireNone => Err(DecodeError::InvalidPadding(symbols_len)),
+        // first excess pad
+        _ if pad_len > expected_pad_len => {
+            Err(DecodeError::InvalidPadding(symbols_len + expected_pad_len))
+        }
+        // padding is missing or does not complete the chunk
+        _ if pad_len < expected_pad_len => Err(DecodeError::InvalidPadding(symbols_len + pad_len)),
+        _ => Ok(()),
+    }
+}
+
+// calculate the maximum number of bytes that encoded_len symbols decode to.
+// exact for unpadded input.
+pub const fn decoded_size(encoded_len: usize) -> usize {
+    let rem = encoded_len % 4;
+    let complete_output_chunks = (encoded_len / 4) * 3;
+
+    // 2 symbols carry 1 byte, 3 symbols carry 2 bytes
+    complete_output_chunks + (rem * 3) / 4
+}
+
+// calculate the exact number of bytes that input decodes to, if it is valid.
+// trailing padding is not counted.
+pub const fn exact_decoded_size(input: &[u8]) -> usize {
+    decoded_size(input.len() - trailing_pad_len(input))
+}
+
+//Decodes Base64 with the STANDARD engine at compile time:
+//
+//static MAGIC: [u8; 3] = base64::decode_array(b"QUJD");
+//
+//N must be exact_decoded_size(input); invalid input panics (or fails to compile in a const).
+//See also base64_bytes!, which infers N.
+pub const fn decode_array<const N: usize>(input: &[u8]) -> [u8; N] {
+    STANDARD.decode_array(input)
+}
+
+//Decodes a Base64 string literal at compile time into a [u8; N], inferring N.
+//Invalid input is a compile error. The engine defaults to STANDARD:
+//
+//static MAGIC: [u8; 3] = base64::base64_bytes!("QUJD");
+//static KEY: [u8; 4] = base64::base64_bytes!(base64::engine::URL_SAFE_NO_PAD, "-_-_-w");
+#[macro_export]
+macro_rules! base64_bytes {
+    ($input:expr) => {
+        $crate::base64_bytes!($crate::engine::STANDARD, $input)
+    };
+    ($engine:expr, $input:expr) => {{
+        const INPUT: &[u8] = $input.as_bytes();
+        const OUTPUT: [u8; $crate::exact_decoded_size(INPUT)] = $engine.decode_array(INPUT);
+        OUTPUT
+    }};
+}
+
+//reads len symbols starting at index into the low bits of a u32
+const fn decode_chunk(
+    input: &[u8],
+    index: usize,
+    len: usize,
+    table: &[u8; 256],
+) -> Result<u32, DecodeError> {
+    let mut chunk: u32 = 0;
+    let mut i = 0;
+
+    while i < len {
+        let byte = input[index + i];
+
+        match table[byte as usize] {
+            INVALID_VALUE if byte == PAD_BYTE => return Err(DecodeError::InvalidPadding(index + i)),
+            INVALID_VALUE => return Err(DecodeError::InvalidByte(index + i, byte)),
+            value => chunk = (chunk << 6) | value as u32,
+        }
+
+        i += 1;
+    }
+
+    Ok(chunk)
+}
+
+//decodes base64 symbols to bytes
+//input must not contain padding
+//output must be long enough to hold decoded_size(input.len()) bytes
+//non-zero trailing bits in the last symbol are rejected
+//Returns the number of bytes written
+pub const fn decode_to_slice(
+    input: &[u8],
+    output: &mut [u8],
+    alphabet: &Alphabet,
+) -> Result<usize, DecodeError> {
+    decode_symbols(input, output, alphabet, false)
+}
+
+//decode_to_slice() that ignores the trailing bits of the last symbol
+//when allow_trailing_bits is set
+pub(crate) const fn decode_symbols(
+    input: &[u8],
+    output: &mut [u8],
+    alphabet: &Alphabet,
+    allow_trailing_bits: bool,
+) -> Result<usize, DecodeError> {
+    let table = &alphabet.decode_table;
+    let mut input_index: usize = 0;
+    let mut output_index: usize = 0;
+
+    let rem = input.len() % 4;
+    let last_index = input.len() - rem;
+
+    while input_index < last_index {
+        //read 4 symbols into the low 24 bits of a u32
+        let output_chunk = match decode_chunk(input, input_index, 4, table) {
+            Ok(chunk) => chunk,
+            Err(e) => return Err(e),
+        };
+
+        output[output_index] = (output_chunk >> 16) as u8;
+        output[output_index + 1] = (output_chunk >> 8) as u8;
+        output[output_index + 2] = output_chunk as u8;
+
+        input_index += 4;
+        output_index += 3;
+    }
+
+    //checked after the complete chunks and the leftover symbol itself,
+    //so that bad symbols are reported first
+    if rem == 1 {
+        return match decode_chunk(input, last_index, 1, table) {
+            Ok(_) => Err(DecodeError::InvalidLength),
+            Err(e) => Err(e),
+        };
+    }
+
+    if rem > 0 {
+        let mut output_chunk = match decode_chunk(input, last_index, rem, table) {
+            Ok(chunk) => chunk,
+            Err(e) => return Err(e),
+        };
+
+        // 2 symbols hold 12 bits (1 byte + 4 spare bits)
+        // 3 symbols hold 18 bits (2 bytes + 2 spare bits)
+        let bytes = rem - 1;
+        let spare_bits = (rem * 6) - (bytes * 8);
+
+        // the encoder always leaves the spare bits zeroed, so anything else
+        // is a second encoding of the same bytes
+        if !allow_trailing_bits && output_chunk & ((1 << spare_bits) - 1) != 0 {
+            let last_index = input.len() - 1;
+            return Err(DecodeError::InvalidLastSymbol(last_index, input[last_index]));
+        }
+
+        output_chunk >>= spare_bits;
+
+        let mut i = 0;
+        while i < bytes {
+            output[output_index + i] = (output_chunk >> (8 * (bytes - 1 - i))) as u8;
+            i += 1;
+        }
+
+        output_index += bytes;
+    }
+
+    Ok(output_index)
+}
+
+//decode_symbols() for use at runtime.
+//the AVX2 decoder takes the valid blocks at the start of input, when the CPU
+//has it, and the portable loop decodes the rest, reporting any error.
+pub(crate) fn decode_symbols_runtime(
+    input: &[u8],
+    output: &mut [u8],
+    alphabet: &Alphabet,
+    allow_trailing_bits: bool,
+) -> Result<usize, DecodeError> {
+    #[cfg(all(feature = "std", target_arch = "x86_64"))]
+    let (read, written) = crate::avx2::decode_prefix(input, output, alphabet);
+    #[cfg(not(all(feature = "std", target_arch = "x86_64")))]
+    let (read, written) = (0, 0);
+
+    match decode_symbols(&input[read..], &mut output[written..], alphabet, allow_trailing_bits) {
+        Ok(len) => Ok(written + len),
+        Err(e) => Err(e.shift_offset(read)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "alloc")]
+    use alloc::{format, string::ToString, vec};
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn check_decode() {
+        let input = include_bytes!("encoded.txt");
+        let output = &include_bytes!("plain.txt")[..];
+
+        assert_eq!(output, &decode(&input[..]).unwrap()[..]);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn check_decode_partial_chunks() {
+        assert_eq!(decode("").unwrap(), b"");
+        assert_eq!(decode("QQ==").unwrap(), b"A");
+        assert_eq!(decode("QUI=").unwrap(), b"AB");
+        assert_eq!(decode("QUJD").unwrap(), b"ABC");
+        assert_eq!(decode("QUI").unwrap(), b"AB");
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn check_decode_errors() {
+        assert_eq!(decode("QU*D"), Err(DecodeError::InvalidByte(2, b'*')));
+        assert_eq!(decode("QUJDR"), Err(DecodeError::InvalidLength));
+        assert_eq!(decode("QUJD\n"), Err(DecodeError::InvalidByte(4, b'\n')));
+        assert_eq!(decode("QUJD*"), Err(DecodeError::InvalidByte(4, b'*')));
+        assert_eq!(decode("Q==="), Err(DecodeError::InvalidLength));
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn check_decode_url_safe() {
+        use crate::engine::URL_SAFE;
+
+        assert_eq!(URL_SAFE.decode("-_-_").unwrap(), [0xfb, 0xff, 0xbf]);
+        assert_eq!(URL_SAFE.decode("+/+/"), Err(DecodeError::InvalidByte(0, b'+')));
+        assert_eq!(decode("-_-_"), Err(DecodeError::InvalidByte(0, b'-')));
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn check_decode_padding_errors() {
+        assert_eq!(decode("QQ=A"), Err(DecodeError::InvalidPadding(2)));
+        assert_eq!(decode("QQ==QUJD"), Err(DecodeError::InvalidPadding(2)));
+        assert_eq!(decode("QQ==="), Err(DecodeError::InvalidPadding(4)));
+        assert_eq!(decode("QUJD="), Err(DecodeError::InvalidPadding(4)));
+        assert_eq!(decode("QQ="), Err(DecodeError::InvalidPadding(3)));
+        assert_eq!(decode("QQ=A="), Err(DecodeError::InvalidPadding(2)));
+        assert_eq!(decode("QR=="), Err(DecodeError::InvalidLastSymbol(1, b'R')));
+        assert_eq!(decode("QUJ="), Err(DecodeError::InvalidLastSymbol(2, b'J')));
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn check_decode_in_place() {
+        for len in (0..3000).step_by(13) {
+            let input = crate::test_util::input(len);
+            let mut buf = crate::encode(&input).into_bytes();
+
+            assert_eq!(&input[..], &decode_in_place(&mut buf).unwrap()[..]);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn check_decode_in_place_errors_match_decode() {
+        use crate::engine::{Config, DecodePaddingMode, GeneralPurpose};
+
+        let canonical = GeneralPurpose::new(
+            Config::new().with_decode_padding_mode(DecodePaddingMode::RequireCanonical),
+        );
+        let encoded = crate::encode(vec![0x3cu8; 1500]);
+
+        for &offset in &[0, 1023, 1024, 1500, encoded.len() - 1] {
+            let mut invalid = encoded.clone().into_bytes();
+            invalid[offset] = b'*';
+
+            assert_eq!(Err(DecodeError::InvalidByte(offset, b'*')), decode_in_place(&mut invalid));
+        }
+
+        for suffix in &["QQ=A", "QQ==QUJD", "QQ===", "QR==", "QQ", "Q", "=QUJD", "QQ=="] {
+            let input = format!("{}{}", &encoded[..1200], suffix);
+            let mut buf = input.clone().into_bytes();
+
+            assert_eq!(
+                canonical.decode(&input),
+                canonical.decode_in_place(&mut buf).map(|decoded| decoded.to_vec())
+            );
+        }
+
+        let mut pads = vec![b'='; 3000];
+        pads[..2].copy_from_slice(b"QQ");
+        assert_eq!(decode(&pads), decode_in_place(&mut pads).map(|decoded| decoded.to_vec()));
+    }
+
+    #[test]
+    fn check_base64_bytes() {
+        static PLAIN: [u8; 322] = crate::base64_bytes!(include_str!("encoded.txt"));
+        const PARTIAL: [u8; 2] = crate::base64_bytes!("QUI=");
+        const URL_SAFE: [u8; 4] = crate::base64_bytes!(crate::engine::URL_SAFE_NO_PAD, "-_-_-w");
+        const EMPTY: [u8; 0] = crate::base64_bytes!("");
+
+        assert_eq!(&include_bytes!("plain.txt")[..], &PLAIN[..]);
+        assert_eq!(b"AB", &PARTIAL);
+        assert_eq!([0xfb, 0xff, 0xbf, 0xfb], URL_SAFE);
+        assert_eq!(b"", &EMPTY);
+    }
+
+    #[test]
+    fn check_decode_array() {
+        const DECODED: [u8; 3] = decode_array(b"QUJD");
+
+        assert_eq!(b"ABC", &DECODED);
+        assert_eq!(2, exact_decoded_size(b"QUI="));
+        assert_eq!(1, exact_decoded_size(b"QQ"));
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid Base64: misplaced padding")]
+    fn check_decode_array_invalid() {
+        let _: [u8; 3] = decode_array(b"QQ=D");
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn check_error_display() {
+        assert_eq!(
+            DecodeError::InvalidByte(2, b'*').to_string(),
+            "Invalid symbol '*', offset 2."
+        );
+        assert_eq!(
+            DecodeError::InvalidByte(0, 0xc3).to_string(),
+            "Invalid symbol 0xc3, offset 0."
+        );
+        assert_eq!(
+            DecodeError::InvalidLastSymbol(1, b'R').to_string(),
+            "Invalid last symbol 'R', offset 1: trailing bits are not zero."
+        );
+    }
+}
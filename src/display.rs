@@ -0,0 +1,150 @@
+use core::fmt;
+
+use crate::encode::add_padding;
+use crate::engine::Engine;
+
+//input bytes encoded at a time, a multiple of 3 so only the last chunk needs padding
+const CHUNK_SIZE: usize = 768;
+
+//Formats bytes as Base64 without allocating, e.g. in format!() or log lines.
+//Input is encoded chunk by chunk into a buffer on the stack.
+pub struct Base64Display<'a, 'e, E: Engine> {
+    bytes: &'a [u8],
+    engine: &'e E,
+}
+
+impl<'a, 'e, E: Engine> Base64Display<'a, 'e, E> {
+    pub fn new(bytes: &'a [u8], engine: &'e E) -> Base64Display<'a, 'e, E> {
+        Base64Display { bytes, engine }
+    }
+}
+
+impl<'a, 'e, E: Engine> fmt::Display for Base64Display<'a, 'e, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut encoder = FmtEncoder::new(f, self.engine);
+
+        encoder.write(self.bytes)?;
+        encoder.finish().map(|_| ())
+    }
+}
+
+//Encodes bytes incrementally into any fmt::Write sink.
+//
+//Up to 2 bytes that do not form a complete chunk are kept between calls to
+//write(), so the sink receives exactly what encode() would return for all
+//the bytes together, once finish() has written the last chunk and padding.
+pub struct FmtEncoder<'e, E: Engine + ?Sized, W: fmt::Write> {
+    engine: &'e E,
+    sink: W,
+    //input bytes left over from the previous write, less than a chunk
+    extra_input: [u8; 3],
+    extra_input_len: usize,
+}
+
+impl<'e, E: Engine + ?Sized, W: fmt::Write> FmtEncoder<'e, E, W> {
+    pub fn new(sink: W, engine: &'e E) -> FmtEncoder<'e, E, W> {
+        FmtEncoder {
+            engine,
+            sink,
+            extra_input: [0u8; 3],
+            extra_input_len: 0,
+        }
+    }
+
+    //encodes input and writes all complete chunks to the sink
+    pub fn write(&mut self, mut input: &[u8]) -> fmt::Result {
+        if self.extra_input_len > 0 {
+            let take = (3 - self.extra_input_len).min(input.len());
+            self.extra_input[self.extra_input_len..(self.extra_input_len + take)]
+                .copy_from_slice(&input[..take]);
+            self.extra_input_len += take;
+            input = &input[take..];
+
+            if self.extra_input_len < 3 {
+                return Ok(());
+            }
+
+            let extra_input = self.extra_input;
+            self.extra_input_len = 0;
+            self.write_encoded(&extra_input)?;
+        }
+
+        let complete_len = input.len() / 3 * 3;
+        for chunk in input[..complete_len].chunks(CHUNK_SIZE) {
+            self.write_encoded(chunk)?;
+        }
+
+        let rem = &input[complete_len..];
+        self.extra_input[..rem.len()].copy_from_slice(rem);
+        self.extra_input_len = rem.len();
+
+        Ok(())
+    }
+
+    //encodes the leftover bytes with padding and returns the sink
+    pub fn finish(mut self) -> Result<W, fmt::Error> {
+        let extra_input = self.extra_input;
+        self.write_encoded(&extra_input[..self.extra_input_len])?;
+
+        Ok(self.sink)
+    }
+
+    //input is at most CHUNK_SIZE bytes, and only padded if it is the last chunk
+    fn write_encoded(&mut self, input: &[u8]) -> fmt::Result {
+        let mut buf = [0u8; CHUNK_SIZE / 3 * 4];
+
+        let mut len = self.engine.internal_encode(input, &mut buf);
+        if self.engine.config().encode_padding() {
+            len += add_padding(input.len(), &mut buf[len..]);
+        }
+
+        self.sink
+            .write_str(core::str::from_utf8(&buf[..len]).expect("Invalid UTF8"))
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use crate::engine::{STANDARD, URL_SAFE_NO_PAD};
+    use crate::test_util::input;
+    use alloc::{format, string::{String, ToString}};
+
+    #[test]
+    fn check_display() {
+        for len in (0..2000).step_by(7).chain(2000..2010) {
+            let input = input(len);
+
+            assert_eq!(STANDARD.encode(&input), Base64Display::new(&input, &STANDARD).to_string());
+            assert_eq!(
+                URL_SAFE_NO_PAD.encode(&input),
+                Base64Display::new(&input, &URL_SAFE_NO_PAD).to_string()
+            );
+        }
+    }
+
+    #[test]
+    fn check_format() {
+        let id = [0xfbu8, 0xff, 0xbf, 0x01];
+
+        assert_eq!(
+            "id=+/+/AQ==;",
+            format!("id={};", Base64Display::new(&id, &STANDARD))
+        );
+    }
+
+    #[test]
+    fn check_fmt_encoder() {
+        let input = input(3000);
+
+        for &fragment in &[1, 2, 3, 4, 5, 767, 768, 769, 2000] {
+            let mut encoder = FmtEncoder::new(String::from("data:"), &STANDARD);
+            for chunk in input.chunks(fragment) {
+                encoder.write(chunk).unwrap();
+            }
+
+            let expected = format!("data:{}", STANDARD.encode(&input));
+            assert_eq!(expected, encoder.finish().unwrap());
+        }
+    }
+}
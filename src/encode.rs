@@ -1,112 +1,232 @@
-const BASE64_TABLE: [u8; 64] = [
-    b'A', b'B', b'C', b'D', b'E', b'F', b'G', b'H', b'I', b'J', b'K', b'L', b'M', b'N', b'O', b'P',
-    b'Q', b'R', b'S', b'T', b'U', b'V', b'W', b'X', b'Y', b'Z', b'a', b'b', b'c', b'd', b'e', b'f',
-    b'g', b'h', b'i', b'j', b'k', b'l', b'm', b'n', b'o', b'p', b'q', b'r', b's', b't', b'u', b'v',
-    b'w', b'x', b'y', b'z', b'0', b'1', b'2', b'3', b'4', b'5', b'6', b'7', b'8', b'9', b'+', b'/',
-];
-const PAD_BYTE: u8 = b'=';
-
-//Encodes arbitrary octets as Base64
+use core::fmt;
+
+#[cfg(feature = "alloc")]
+use alloc::{string::String, vec::Vec};
+
+use crate::alphabet::Alphabet;
+use crate::engine::{Engine, STANDARD};
+use crate::table::{encode_to_slice_double, DoubleSymbolTable};
+
+pub(crate) const PAD_BYTE: u8 = b'=';
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EncodeSliceError {
+    //output buffer can not hold the encoded input.
+    //needed is the encoded size, saturated at usize::MAX if it overflows.
+    BufferTooSmall { needed: usize },
+}
+
+impl fmt::Display for EncodeSliceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            EncodeSliceError::BufferTooSmall { needed } => {
+                write!(f, "Output buffer too small, {} bytes needed.", needed)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for EncodeSliceError {}
+
+//Encodes arbitrary octets as Base64 with the STANDARD engine
 //Returns a string.
+#[cfg(feature = "alloc")]
 pub fn encode<T: AsRef<[u8]>>(input: T) -> String {
-    let len = encode_size(input.as_ref().len());
-    let mut buf = vec![0u8; len];
+    STANDARD.encode(input)
+}
 
-    encode_with_padding(input.as_ref(), &mut buf, len);
+//Appends arbitrary octets encoded as Base64 with the STANDARD engine
+//to an existing string.
+#[cfg(feature = "alloc")]
+pub fn encode_string<T: AsRef<[u8]>>(input: T, output: &mut String) {
+    STANDARD.encode_string(input, output)
+}
 
-    String::from_utf8(buf).expect("Invalid UTF8")
+//Appends arbitrary octets encoded as Base64 with the STANDARD engine
+//to an existing buffer.
+#[cfg(feature = "alloc")]
+pub fn encode_vec<T: AsRef<[u8]>>(input: T, output: &mut Vec<u8>) {
+    STANDARD.encode_vec(input, output)
 }
 
-//Encode arbitrary octets as Base64.
+//Encode arbitrary octets as Base64 with the STANDARD engine.
 //Writes into the given buffer.
 //It is useful for writing to pre-allocated memory like in the stack.
-pub fn encode_slice<T: AsRef<[u8]>>(input: T, output: &mut [u8]) -> usize {
-    let input = input.as_ref();
-    
-    let encode_size = encode_size(input.len());
-    let mut b64_output = &mut output[..encode_size];//only required amount of space in buffer is used obviously
-
-    encode_with_padding(input, &mut b64_output, encode_size);
+//Returns the number of bytes written, or an error if output is too small.
+pub fn encode_slice<T: AsRef<[u8]>>(
+    input: T,
+    output: &mut [u8],
+) -> Result<usize, EncodeSliceError> {
+    STANDARD.encode_slice(input, output)
+}
 
-    encode_size
+//Encodes arbitrary octets as Base64 with the STANDARD engine, using up to
+//`threads` threads. Writes into the given buffer.
+//Returns the number of bytes written, or an error if output is too small.
+#[cfg(feature = "std")]
+pub fn encode_parallel<T: AsRef<[u8]>>(
+    input: T,
+    output: &mut [u8],
+    threads: usize,
+) -> Result<usize, EncodeSliceError> {
+    STANDARD.encode_parallel(input, output, threads)
 }
 
-//this helper function combines the two functions encode_to_slice() and add_padding().
+//this helper function combines the engine's internal_encode() and add_padding().
 //writes into the supplied output buffer whose length must be equal to the size of encoded input.
 //encoded_size is the size calculated for input.
 //
-fn encode_with_padding(input: &[u8], output: &mut [u8], encoded_size: usize) {
+pub(crate) fn encode_with_padding<E: Engine + ?Sized>(
+    input: &[u8],
+    output: &mut [u8],
+    encoded_size: usize,
+    engine: &E,
+) {
     debug_assert_eq!(output.len(), encoded_size);
 
-    let b64_bytes = encode_to_slice(input, output);
-    let padding_bytes = add_padding( input.len(), &mut output[b64_bytes..]);
+    let b64_bytes = engine.internal_encode(input, output);
+    let padding_bytes = if engine.config().encode_padding() {
+        add_padding( input.len(), &mut output[b64_bytes..])
+    } else {
+        0
+    };
 
     let encoded_bytes = b64_bytes + padding_bytes;
     debug_assert_eq!(encoded_bytes, encoded_size);
 }
 
-// caluclate size of base64 string including padding
-pub fn encode_size(input_len: usize) -> usize {
+// calculate size of base64 string, with or without padding
+// panics if the size does not fit in a usize, rather than returning a wrapped length
+pub const fn encode_size(input_len: usize, padding: bool) -> usize {
+    checked_encode_size(input_len, padding).expect("usize overflow when calculating encoded size")
+}
+
+// calculate size of base64 string, with or without padding
+// Returns None if the size does not fit in a usize.
+pub const fn checked_encode_size(input_len: usize, padding: bool) -> Option<usize> {
     let rem = input_len % 3;
     let input_chunks_complete = input_len / 3;
-    let complete_output_chunks = input_chunks_complete * 4;
+    let complete_output_chunks = match input_chunks_complete.checked_mul(4) {
+        Some(len) => len,
+        None => return None,
+    };
 
-    if rem > 0 {
+    if rem > 0 && padding {
         // padding included
-        complete_output_chunks + 4
+        complete_output_chunks.checked_add(4)
     } else {
-        complete_output_chunks
+        // 1 byte takes 2 symbols, 2 bytes take 3 symbols
+        complete_output_chunks.checked_add(rem + (rem > 0) as usize)
     }
 }
 
-fn read_u32(s: &[u8]) -> u32 {
-    let temp = [0, s[0], s[1], s[2] ];
+//Encodes a byte array as Base64 with the STANDARD engine at compile time:
+//
+//static TOKEN: [u8; 8] = base64::encode_array(b"user:");
+//
+//M must be encode_size(N, true), otherwise this panics (or fails to compile in a const).
+pub const fn encode_array<const N: usize, const M: usize>(input: &[u8; N]) -> [u8; M] {
+    STANDARD.encode_array(input)
+}
+
+const fn read_u32(s: &[u8], index: usize) -> u32 {
+    let temp = [0, s[index], s[index + 1], s[index + 2] ];
     u32::from_be_bytes( temp)
 }
 
+//the wide loop encodes 12 bytes per iteration as 16 symbols,
+//reading them as two u64s whose last 2 bytes are ignored
+const WIDE_INPUT_LEN: usize = 12;
+const WIDE_READ_LEN: usize = 14;
+const WIDE_OUTPUT_LEN: usize = 16;
+
+//reads block[index..index + 8] as a big-endian u64
+const fn read_u64(block: &[u8; WIDE_READ_LEN], index: usize) -> u64 {
+    let mut bytes = [0u8; 8];
+    let mut i = 0;
+    while i < 8 {
+        bytes[i] = block[index + i];
+        i += 1;
+    }
+
+    u64::from_be_bytes(bytes)
+}
+
+//writes the 8 symbols of the high 48 bits of word to output[index..index + 8]
+const fn write_symbols(
+    word: u64,
+    table: &[u8; 64],
+    output: &mut [u8; WIDE_OUTPUT_LEN],
+    index: usize,
+) {
+    let mut i = 0;
+    while i < 8 {
+        output[index + i] = table[((word >> (58 - 6 * i)) & 0x3f) as usize];
+        i += 1;
+    }
+}
+
 //encodes input to base64 bytes
 //output must be long enough to hold the encoded 'input' without padding
 //Returns the number of bytes written
-pub fn encode_to_slice(input: &[u8], output: &mut [u8]) -> usize {
+#[allow(clippy::identity_op)]
+pub const fn encode_to_slice(input: &[u8], output: &mut [u8], alphabet: &Alphabet) -> usize {
+    let table = &alphabet.encode_table;
     let mut input_index: usize = 0;
     let mut output_index: usize = 0;
 
     const LOW_SIX_BITS: u32 = 0x3f;
     const LOW_SIX_BITS_U8: u8 = 0x3f;
 
+    //whole blocks first, with the bounds checked once per block rather than per byte
+    while input.len() - input_index >= WIDE_READ_LEN
+        && output.len() - output_index >= WIDE_OUTPUT_LEN
+    {
+        let block = match input.split_at(input_index).1.first_chunk::<WIDE_READ_LEN>() {
+            Some(block) => block,
+            None => break,
+        };
+        let out = match output.split_at_mut(output_index).1.first_chunk_mut::<WIDE_OUTPUT_LEN>() {
+            Some(out) => out,
+            None => break,
+        };
+
+        write_symbols(read_u64(block, 0), table, out, 0);
+        write_symbols(read_u64(block, 6), table, out, 8);
+
+        input_index += WIDE_INPUT_LEN;
+        output_index += WIDE_OUTPUT_LEN;
+    }
+
     let rem = input.len() % 3;
     let last_index = input.len() - rem;
 
     while input_index < last_index {
 
         //read 3 bytes into u32
-        let input_chunk = read_u32(&input[input_index..(input_index + 3) ]);
-        let output_chunk = &mut output[output_index..(output_index + 4) ];
+        let input_chunk = read_u32(input, input_index);
 
-        output_chunk[0] = BASE64_TABLE[ ( (input_chunk >> 18) & LOW_SIX_BITS) as  usize];
-        output_chunk[1] = BASE64_TABLE[ ( (input_chunk >> 12) & LOW_SIX_BITS) as  usize];
-        output_chunk[2] = BASE64_TABLE[ ( (input_chunk >> 6) & LOW_SIX_BITS) as  usize];
-        output_chunk[3] = BASE64_TABLE[ ( (input_chunk >> 0) & LOW_SIX_BITS) as  usize];
+        output[output_index] = table[ ( (input_chunk >> 18) & LOW_SIX_BITS) as  usize];
+        output[output_index + 1] = table[ ( (input_chunk >> 12) & LOW_SIX_BITS) as  usize];
+        output[output_index + 2] = table[ ( (input_chunk >> 6) & LOW_SIX_BITS) as  usize];
+        output[output_index + 3] = table[ ( (input_chunk >> 0) & LOW_SIX_BITS) as  usize];
 
         input_index += 3;
         output_index += 4;
     }
 
     if rem == 2 {
-        let output_chunk = &mut output[output_index..(output_index + 3)];
-
-        output_chunk[0] = BASE64_TABLE[ ((input[last_index] >> 2) & LOW_SIX_BITS_U8) as usize];
-        output_chunk[1] = BASE64_TABLE[ (((input[last_index] << 4) 
+        output[output_index] = table[ ((input[last_index] >> 2) & LOW_SIX_BITS_U8) as usize];
+        output[output_index + 1] = table[ (((input[last_index] << 4) 
                 | (input[last_index + 1] >> 4) ) 
                 & LOW_SIX_BITS_U8) as usize];
-        output_chunk[2] = BASE64_TABLE[ ((input[last_index + 1] << 2) & LOW_SIX_BITS_U8) as usize];
+        output[output_index + 2] = table[ ((input[last_index + 1] << 2) & LOW_SIX_BITS_U8) as usize];
 
         output_index += 3;
     } else if rem == 1 {
-        let output_chunk = &mut output[output_index..(output_index + 2)];
-
-        output_chunk[0] = BASE64_TABLE[ ((input[last_index] >> 2) & LOW_SIX_BITS_U8) as usize];
-        output_chunk[1] = BASE64_TABLE[ ((input[last_index] << 4) & LOW_SIX_BITS_U8) as usize];
+        output[output_index] = table[ ((input[last_index] >> 2) & LOW_SIX_BITS_U8) as usize];
+        output[output_index + 1] = table[ ((input[last_index] << 4) & LOW_SIX_BITS_U8) as usize];
 
         output_index += 2;
     }
@@ -114,16 +234,40 @@ pub fn encode_to_slice(input: &[u8], output: &mut [u8]) -> usize {
     output_index
 }
 
+//inputs from this size are encoded with a DoubleSymbolTable built for the call
+const DOUBLE_SYMBOL_MIN_LEN: usize = 32 * 1024;
+
+//encode_to_slice() for use at runtime.
+//the AVX2 encoder takes whatever part of input it can, when the CPU has it.
+//the rest is encoded two symbols at a time if it is large enough to be worth
+//building the table, or else by the portable loop.
+pub(crate) fn encode_to_slice_runtime(input: &[u8], output: &mut [u8], alphabet: &Alphabet) -> usize {
+    #[cfg(all(feature = "std", target_arch = "x86_64"))]
+    let (read, written) = crate::avx2::encode_prefix(input, output, alphabet);
+    #[cfg(not(all(feature = "std", target_arch = "x86_64")))]
+    let (read, written) = (0, 0);
+
+    let (input, output) = (&input[read..], &mut output[written..]);
+
+    if input.len() >= DOUBLE_SYMBOL_MIN_LEN {
+        written + encode_to_slice_double(input, output, &DoubleSymbolTable::new(alphabet))
+    } else {
+        written + encode_to_slice(input, output, alphabet)
+    }
+}
+
 //writes padding bytes to output
 //Returns number of bytes written.
 //
 //output must be of length at least 2.
-pub fn add_padding( input_len: usize, output: &mut [u8] ) -> usize {
+pub const fn add_padding( input_len: usize, output: &mut [u8] ) -> usize {
     let rem = input_len % 3;
     let len = (3 - rem) % 3;
 
-    for i in 0..len {
+    let mut i = 0;
+    while i < len {
         output[i] = PAD_BYTE;
+        i += 1;
     }
 
     len
@@ -141,10 +285,11 @@ mod tests {
         let in_len = input.len();
         let out_len = output.len();
 
-        assert_eq!(out_len, encode_size(in_len));
+        assert_eq!(out_len, encode_size(in_len, true));
     }
 
     #[test]
+    #[cfg(feature = "alloc")]
     fn check_encode_with_padding() {
         let input = include_bytes!("plain.txt");
         let output = &include_bytes!("encoded.txt")[..];
@@ -152,9 +297,105 @@ mod tests {
         let in_len = input.len();
         let out_len = output.len();
 
-        let mut buf = vec![0u8; encode_size(in_len)];
-        encode_with_padding(input, &mut buf, out_len);
+        let mut buf = alloc::vec![0u8; encode_size(in_len, true)];
+        encode_with_padding(input, &mut buf, out_len, &STANDARD);
 
         assert_eq!(output, buf);
     }
+
+    #[test]
+    fn check_size_unpadded() {
+        assert_eq!(0, encode_size(0, false));
+        assert_eq!(2, encode_size(1, false));
+        assert_eq!(3, encode_size(2, false));
+        assert_eq!(4, encode_size(3, false));
+        assert_eq!(6, encode_size(4, false));
+    }
+
+    #[test]
+    fn check_encode_slice_too_small() {
+        let mut buf = [0u8; 8];
+
+        assert_eq!(Ok(8), encode_slice(b"ABCDE", &mut buf));
+        assert_eq!(b"QUJDREU=", &buf);
+        assert_eq!(
+            Err(EncodeSliceError::BufferTooSmall { needed: 12 }),
+            encode_slice(b"ABCDEFG", &mut buf)
+        );
+        assert_eq!(
+            Err(EncodeSliceError::BufferTooSmall { needed: 8 }),
+            encode_slice(b"ABCDE", &mut buf[..7])
+        );
+    }
+
+    #[test]
+    fn check_decode_slice_too_small() {
+        use crate::decode::{decode_slice, DecodeError, DecodeSliceError};
+
+        let mut buf = [0u8; 48];
+
+        assert_eq!(Ok(5), decode_slice("QUJDREU=", &mut buf[..5]));
+        assert_eq!(b"ABCDE", &buf[..5]);
+        assert_eq!(
+            Err(DecodeSliceError::OutputTooSmall { needed: 3 }),
+            decode_slice("QUJD", &mut buf[..2])
+        );
+        assert_eq!(
+            Err(DecodeSliceError::OutputTooSmall { needed: 48 }),
+            decode_slice([b'Q'; 64], &mut buf[..47])
+        );
+        assert_eq!(
+            Err(DecodeSliceError::Decode(DecodeError::InvalidByte(2, b'*'))),
+            decode_slice("QU*D", &mut buf)
+        );
+    }
+
+    #[test]
+    fn check_size_overflow() {
+        // largest input whose padded encoding still fits
+        let max_input = usize::MAX / 4 * 3;
+
+        assert_eq!(Some(usize::MAX / 4 * 4), checked_encode_size(max_input, true));
+        assert_eq!(None, checked_encode_size(max_input + 1, true));
+        assert_eq!(Some(usize::MAX / 4 * 4 + 2), checked_encode_size(max_input + 1, false));
+        assert_eq!(None, checked_encode_size(usize::MAX, false));
+    }
+
+    #[test]
+    fn check_encode_array() {
+        static PLAIN: [u8; 4] = encode_array(b"ABC");
+        const PADDED: [u8; 8] = encode_array(b"ABCDE");
+        const EMPTY: [u8; 0] = encode_array(b"");
+
+        assert_eq!(b"QUJD", &PLAIN);
+        assert_eq!(b"QUJDREU=", &PADDED);
+        assert_eq!(b"", &EMPTY);
+    }
+
+    #[test]
+    fn check_encode_to_slice_block_boundaries() {
+        let mut input = [0u8; 100];
+        for (i, byte) in input.iter_mut().enumerate() {
+            *byte = (i as u8).wrapping_mul(151);
+        }
+
+        for len in 0..input.len() {
+            let mut encoded = [0u8; 136];
+            let written = encode_to_slice(&input[..len], &mut encoded, &Alphabet::STANDARD);
+            assert_eq!(encode_size(len, false), written);
+
+            // the wide loop may only differ from the 3-byte loop in speed
+            let mut expected = [0u8; 136];
+            for (i, chunk) in input[..len].chunks(3).enumerate() {
+                encode_to_slice(chunk, &mut expected[(i * 4)..], &Alphabet::STANDARD);
+            }
+            assert_eq!(&expected[..written], &encoded[..written]);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "usize overflow")]
+    fn check_size_overflow_panics() {
+        encode_size(usize::MAX, true);
+    }
 }
@@ -0,0 +1,562 @@
+#[cfg(feature = "alloc")]
+use alloc::{string::String, vec, vec::Vec};
+
+use crate::alphabet::Alphabet;
+#[cfg(feature = "alloc")]
+use crate::decode::decoded_size;
+use crate::decode::{
+    decode_in_place_with_padding, decode_symbols_runtime, decode_with_config,
+    decode_with_padding, exact_decoded_size, DecodeError, DecodeSliceError,
+};
+#[cfg(feature = "alloc")]
+use crate::display::FmtEncoder;
+use crate::encode::{
+    add_padding, checked_encode_size, encode_size, encode_to_slice, encode_to_slice_runtime,
+    encode_with_padding, EncodeSliceError,
+};
+
+//An Engine turns octets into Base64 and back.
+//Implementors provide the conversion of complete symbols; padding, buffer
+//sizing and allocation are handled by the provided methods, following config().
+pub trait Engine {
+    fn config(&self) -> &Config;
+
+    //encodes input to base64 symbols, without padding
+    //output must be long enough to hold encode_size(input.len(), false) bytes
+    //Returns the number of bytes written
+    fn internal_encode(&self, input: &[u8], output: &mut [u8]) -> usize;
+
+    //decodes base64 symbols, which must not include padding
+    //output must be long enough to hold decoded_size(input.len()) bytes
+    //Returns the number of bytes written
+    fn internal_decode(&self, input: &[u8], output: &mut [u8]) -> Result<usize, DecodeError>;
+
+    //Encodes arbitrary octets as Base64.
+    //Returns a string.
+    #[cfg(feature = "alloc")]
+    fn encode<T: AsRef<[u8]>>(&self, input: T) -> String {
+        let input = input.as_ref();
+        let len = encode_size(input.len(), self.config().encode_padding());
+        let mut buf = vec![0u8; len];
+
+        encode_with_padding(input, &mut buf, len, self);
+
+        String::from_utf8(buf).expect("Invalid UTF8")
+    }
+
+    //Appends the Base64 encoding of input to output.
+    //Grows output once by the encoded size and encodes straight into it.
+    #[cfg(feature = "alloc")]
+    fn encode_vec<T: AsRef<[u8]>>(&self, input: T, output: &mut Vec<u8>) {
+        let input = input.as_ref();
+        let len = encode_size(input.len(), self.config().encode_padding());
+        let start = output.len();
+
+        output.reserve(len);
+        output.resize(start + len, 0);
+
+        encode_with_padding(input, &mut output[start..], len, self);
+    }
+
+    //Appends the Base64 encoding of input to output.
+    //Grows output once by the encoded size, then appends the encoding chunk by
+    //chunk; output keeps its contents even if growing it panics.
+    #[cfg(feature = "alloc")]
+    fn encode_string<T: AsRef<[u8]>>(&self, input: T, output: &mut String) {
+        let input = input.as_ref();
+        output.reserve(encode_size(input.len(), self.config().encode_padding()));
+
+        let mut encoder = FmtEncoder::new(output, self);
+        encoder
+            .write(input)
+            .and_then(|_| encoder.finish())
+            .expect("writing to a String can not fail");
+    }
+
+    //Encode arbitrary octets as Base64.
+    //Writes into the given buffer, without panicking if it is too small.
+    //Returns the number of bytes written, or the size needed in the error.
+    fn encode_slice<T: AsRef<[u8]>>(
+        &self,
+        input: T,
+        output: &mut [u8],
+    ) -> Result<usize, EncodeSliceError> {
+        let input = input.as_ref();
+        let encode_size = checked_output_size(input.len(), self.config(), output)?;
+
+        let b64_output = &mut output[..encode_size];
+
+        encode_with_padding(input, b64_output, encode_size, self);
+
+        Ok(encode_size)
+    }
+
+    //encode_slice() split over up to `threads` threads, for large inputs.
+    //Each thread encodes a segment of whole 3-byte chunks, of at least 64 KiB;
+    //the calling thread encodes the last segment, with the bytes left over,
+    //and writes the padding.
+    #[cfg(feature = "std")]
+    fn encode_parallel<T: AsRef<[u8]>>(
+        &self,
+        input: T,
+        output: &mut [u8],
+        threads: usize,
+    ) -> Result<usize, EncodeSliceError>
+    where
+        Self: Sync,
+    {
+        let input = input.as_ref();
+        let encode_size = checked_output_size(input.len(), self.config(), output)?;
+
+        let (segments, segment_len) = split_segments(input.len(), threads);
+
+        std::thread::scope(|scope| {
+            let mut input = input;
+            let mut rest = &mut output[..encode_size];
+
+            for _ in 1..segments {
+                let (segment, next_input) = input.split_at(segment_len);
+                let (segment_output, next) =
+                    core::mem::take(&mut rest).split_at_mut(segment_len / 3 * 4);
+                input = next_input;
+                rest = next;
+
+                scope.spawn(move || self.internal_encode(segment, segment_output));
+            }
+
+            let rest_len = rest.len();
+            encode_with_padding(input, rest, rest_len, self);
+        });
+
+        Ok(encode_size)
+    }
+
+    //Decodes Base64 into arbitrary octets.
+    //Returns a Vec<u8>.
+    #[cfg(feature = "alloc")]
+    fn decode<T: AsRef<[u8]>>(&self, input: T) -> Result<Vec<u8>, DecodeError> {
+        let input = input.as_ref();
+        let mut buf = vec![0u8; decoded_size(input.len())];
+
+        let len = decode_with_padding(input, &mut buf, self)?;
+        buf.truncate(len);
+
+        Ok(buf)
+    }
+
+    //Decodes Base64 into the given buffer.
+    //Returns the number of bytes written, or an error if input is invalid or
+    //output is shorter than exact_decoded_size(input).
+    fn decode_slice<T: AsRef<[u8]>>(
+        &self,
+        input: T,
+        output: &mut [u8],
+    ) -> Result<usize, DecodeSliceError> {
+        let input = input.as_ref();
+        let needed = exact_decoded_size(input);
+
+        if needed > output.len() {
+            return Err(DecodeSliceError::OutputTooSmall { needed });
+        }
+
+        Ok(decode_with_padding(input, output, self)?)
+    }
+
+    //Decodes Base64 over the start of buf itself, with the same checks as decode().
+    //Returns the decoded part of buf; on error the contents of buf are unspecified.
+    fn decode_in_place<'a>(&self, buf: &'a mut [u8]) -> Result<&'a mut [u8], DecodeError> {
+        decode_in_place_with_padding(buf, self)
+    }
+}
+
+//the encoded size of input_len bytes, or an error if it does not fit in output
+fn checked_output_size(
+    input_len: usize,
+    config: &Config,
+    output: &[u8],
+) -> Result<usize, EncodeSliceError> {
+    // an overflowing size is larger than any buffer
+    let encode_size = checked_encode_size(input_len, config.encode_padding()).unwrap_or(usize::MAX);
+
+    if encode_size > output.len() {
+        return Err(EncodeSliceError::BufferTooSmall { needed: encode_size });
+    }
+
+    Ok(encode_size)
+}
+
+//3-byte chunks in the smallest segment encode_parallel() hands to a thread
+#[cfg(feature = "std")]
+const MIN_SEGMENT_CHUNKS: usize = 64 * 1024 / 3;
+
+//Splits input_len bytes for encode_parallel() into at most `threads` segments
+//of at least MIN_SEGMENT_CHUNKS chunks.
+//Returns the number of segments and the length of all but the last one, which
+//also takes the bytes left over.
+#[cfg(feature = "std")]
+fn split_segments(input_len: usize, threads: usize) -> (usize, usize) {
+    let chunks = input_len / 3;
+    let segments = threads.min(chunks / MIN_SEGMENT_CHUNKS).max(1);
+
+    (segments, chunks / segments * 3)
+}
+
+//How the decoder treats trailing PAD_BYTEs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodePaddingMode {
+    //padding may be left out, but when present it must be canonical
+    Indifferent,
+    //canonical padding is required, as produced by a padding encoder
+    RequireCanonical,
+    //padding is rejected, as in JWTs and other unpadded formats
+    RequireNone,
+}
+
+//Settings shared by the encoding and decoding side of an engine
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    alphabet: Alphabet,
+    encode_padding: bool,
+    decode_padding_mode: DecodePaddingMode,
+    decode_allow_trailing_bits: bool,
+}
+
+impl Config {
+    //standard alphabet, padded output, decoding accepts input with or without padding
+    //and rejects non-canonical trailing bits
+    pub const fn new() -> Config {
+        Config {
+            alphabet: Alphabet::STANDARD,
+            encode_padding: true,
+            decode_padding_mode: DecodePaddingMode::Indifferent,
+            decode_allow_trailing_bits: false,
+        }
+    }
+
+    pub const fn with_alphabet(self, alphabet: Alphabet) -> Config {
+        Config { alphabet, ..self }
+    }
+
+    pub const fn with_encode_padding(self, encode_padding: bool) -> Config {
+        Config {
+            encode_padding,
+            ..self
+        }
+    }
+
+    pub const fn with_decode_padding_mode(self, decode_padding_mode: DecodePaddingMode) -> Config {
+        Config {
+            decode_padding_mode,
+            ..self
+        }
+    }
+
+    //lenient decoding: ignore set bits in the last symbol that do not belong to any
+    //decoded byte, so that e.g. "QR==" decodes like "QQ==".
+    //strict decoding (the default) rejects them with DecodeError::InvalidLastSymbol.
+    pub const fn with_decode_allow_trailing_bits(self, decode_allow_trailing_bits: bool) -> Config {
+        Config {
+            decode_allow_trailing_bits,
+            ..self
+        }
+    }
+
+    pub const fn alphabet(&self) -> &Alphabet {
+        &self.alphabet
+    }
+
+    pub const fn encode_padding(&self) -> bool {
+        self.encode_padding
+    }
+
+    pub const fn decode_padding_mode(&self) -> DecodePaddingMode {
+        self.decode_padding_mode
+    }
+
+    pub const fn decode_allow_trailing_bits(&self) -> bool {
+        self.decode_allow_trailing_bits
+    }
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config::new()
+    }
+}
+
+//Engine built on the portable encode_to_slice() and decode_to_slice() loops.
+//At runtime, both use AVX2 instead where the CPU supports it, and large
+//inputs are otherwise encoded with a DoubleSymbolTable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GeneralPurpose {
+    config: Config,
+}
+
+impl GeneralPurpose {
+    pub const fn new(config: Config) -> GeneralPurpose {
+        GeneralPurpose { config }
+    }
+
+    //Encodes a byte array as Base64 at compile time, e.g. into a static:
+    //
+    //static TOKEN: [u8; 7] = URL_SAFE_NO_PAD.encode_array(b"user:");
+    //
+    //M must be encode_size(N, padding), otherwise this panics (or fails to compile in a const).
+    pub const fn encode_array<const N: usize, const M: usize>(&self, input: &[u8; N]) -> [u8; M] {
+        assert!(
+            M == encode_size(N, self.config.encode_padding),
+            "output array length must be the encoded size of the input"
+        );
+
+        let mut output = [0u8; M];
+        let written = encode_to_slice(input, &mut output, &self.config.alphabet);
+
+        if self.config.encode_padding {
+            add_padding(N, output.split_at_mut(written).1);
+        }
+
+        output
+    }
+
+    //Decodes Base64 into a byte array at compile time, e.g. into a static.
+    //N must be exact_decoded_size(input); invalid input panics (or fails to
+    //compile in a const). See also base64_bytes!, which infers N.
+    pub const fn decode_array<const N: usize>(&self, input: &[u8]) -> [u8; N] {
+        assert!(
+            N == exact_decoded_size(input),
+            "output array length must be the decoded size of the input"
+        );
+
+        let mut output = [0u8; N];
+
+        // const panics can not format the offset, see decode() for the full error
+        match decode_with_config(input, &mut output, &self.config) {
+            Ok(_) => output,
+            Err(DecodeError::InvalidByte(..)) => panic!("invalid Base64: symbol not in the alphabet"),
+            Err(DecodeError::InvalidLength) => panic!("invalid Base64: invalid length"),
+            Err(DecodeError::InvalidPadding(_)) => panic!("invalid Base64: misplaced padding"),
+            Err(DecodeError::InvalidLastSymbol(..)) => {
+                panic!("invalid Base64: non-zero trailing bits")
+            }
+        }
+    }
+}
+
+impl Engine for GeneralPurpose {
+    fn config(&self) -> &Config {
+        &self.config
+    }
+
+    fn internal_encode(&self, input: &[u8], output: &mut [u8]) -> usize {
+        encode_to_slice_runtime(input, output, &self.config.alphabet)
+    }
+
+    fn internal_decode(&self, input: &[u8], output: &mut [u8]) -> Result<usize, DecodeError> {
+        decode_symbols_runtime(
+            input,
+            output,
+            &self.config.alphabet,
+            self.config.decode_allow_trailing_bits,
+        )
+    }
+}
+
+//standard alphabet with padding
+pub const STANDARD: GeneralPurpose = GeneralPurpose::new(Config::new());
+
+//standard alphabet without padding
+pub const STANDARD_NO_PAD: GeneralPurpose =
+    GeneralPurpose::new(Config::new().with_encode_padding(false));
+
+//URL-safe alphabet with padding
+pub const URL_SAFE: GeneralPurpose =
+    GeneralPurpose::new(Config::new().with_alphabet(Alphabet::URL_SAFE));
+
+//URL-safe alphabet without padding
+pub const URL_SAFE_NO_PAD: GeneralPurpose = GeneralPurpose::new(
+    Config::new()
+        .with_alphabet(Alphabet::URL_SAFE)
+        .with_encode_padding(false),
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "alloc")]
+    use alloc::format;
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn check_encode_padding() {
+        let input = [0xfbu8, 0xff, 0xbf, 0xfb];
+
+        assert_eq!("+/+/+w==", STANDARD.encode(input));
+        assert_eq!("+/+/+w", STANDARD_NO_PAD.encode(input));
+        assert_eq!("-_-_-w==", URL_SAFE.encode(input));
+        assert_eq!("-_-_-w", URL_SAFE_NO_PAD.encode(input));
+    }
+
+    #[test]
+    fn check_encode_slice_unpadded() {
+        let mut buf = [0u8; 8];
+
+        assert_eq!(Ok(6), STANDARD_NO_PAD.encode_slice(b"ABCD", &mut buf));
+        assert_eq!(b"QUJDRA", &buf[..6]);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn check_encode_append() {
+        let mut header = String::from("Basic ");
+        let mut body = b"{\"key\":\"".to_vec();
+
+        STANDARD.encode_string(b"user:pass", &mut header);
+        URL_SAFE_NO_PAD.encode_vec([0xfbu8, 0xff], &mut body);
+        body.push(b'"');
+
+        assert_eq!("Basic dXNlcjpwYXNz", header);
+        assert_eq!(&b"{\"key\":\"-_8\""[..], &body[..]);
+
+        // appended over several chunks
+        let input = vec![0x6bu8; 2000];
+        STANDARD.encode_string(&input, &mut header);
+        assert_eq!(format!("Basic dXNlcjpwYXNz{}", STANDARD.encode(&input)), header);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn check_encode_parallel() {
+        let input = crate::test_util::input(1000);
+
+        for &engine in &[STANDARD, URL_SAFE_NO_PAD] {
+            for len in (0..40).chain(997..1000) {
+                let expected = engine.encode(&input[..len]);
+
+                for threads in 0..6 {
+                    let mut output = vec![0u8; expected.len() + 2];
+
+                    assert_eq!(
+                        Ok(expected.len()),
+                        engine.encode_parallel(&input[..len], &mut output, threads)
+                    );
+                    assert_eq!(expected.as_bytes(), &output[..expected.len()]);
+                }
+            }
+        }
+
+        let input = crate::test_util::input(MIN_SEGMENT_CHUNKS * 3 * 3 + 2);
+        let expected = STANDARD.encode(&input);
+
+        for threads in 1..6 {
+            let mut output = vec![0u8; expected.len()];
+
+            assert_eq!(Ok(expected.len()), STANDARD.encode_parallel(&input, &mut output, threads));
+            assert_eq!(expected.as_bytes(), &output[..]);
+        }
+
+        assert_eq!(
+            Err(EncodeSliceError::BufferTooSmall { needed: 8 }),
+            STANDARD.encode_parallel(b"ABCDE", &mut [0u8; 7], 2)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn check_split_segments() {
+        let min_len = MIN_SEGMENT_CHUNKS * 3;
+
+        assert_eq!((1, 6), split_segments(7, 2));
+        assert_eq!((1, min_len * 3), split_segments(min_len * 3 + 2, 0));
+        assert_eq!((3, min_len), split_segments(min_len * 3 + 2, 100_000));
+
+        for &len in &[0, 1, 7, min_len - 1, min_len, 2 * min_len + 5, 10 * min_len + 2] {
+            for &threads in &[0, 1, 2, 3, 7, 100_000] {
+                let (segments, segment_len) = split_segments(len, threads);
+
+                assert!(segments <= threads.max(1));
+                assert_eq!(0, segment_len % 3);
+                assert!(segments * segment_len <= len);
+                assert!(segments == 1 || segment_len >= min_len);
+            }
+        }
+    }
+
+    #[test]
+    fn check_encode_array() {
+        static URL_TOKEN: [u8; 6] = URL_SAFE_NO_PAD.encode_array(&[0xfb, 0xff, 0xbf, 0xfb]);
+        const CRYPT: Alphabet = match Alphabet::new(
+            "./0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz",
+        ) {
+            Ok(alphabet) => alphabet,
+            Err(_) => panic!("invalid alphabet"),
+        };
+        const CUSTOM: GeneralPurpose = GeneralPurpose::new(Config::new().with_alphabet(CRYPT));
+        const CUSTOM_TOKEN: [u8; 8] = CUSTOM.encode_array(b"ABCD");
+
+        assert_eq!(b"-_-_-w", &URL_TOKEN);
+        assert_eq!(b"EI71F.==", &CUSTOM_TOKEN);
+    }
+
+    #[test]
+    #[should_panic(expected = "output array length")]
+    fn check_encode_array_length() {
+        let _: [u8; 4] = STANDARD_NO_PAD.encode_array(b"ABCD");
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn check_decode_padding_mode() {
+        let canonical = GeneralPurpose::new(
+            Config::new().with_decode_padding_mode(DecodePaddingMode::RequireCanonical),
+        );
+
+        assert_eq!(b"A", &STANDARD.decode("QQ==").unwrap()[..]);
+        assert_eq!(b"A", &STANDARD.decode("QQ").unwrap()[..]);
+        assert_eq!(b"A", &canonical.decode("QQ==").unwrap()[..]);
+        assert_eq!(Err(DecodeError::InvalidPadding(2)), canonical.decode("QQ"));
+        assert_eq!(Err(DecodeError::InvalidPadding(3)), canonical.decode("QQ="));
+        assert_eq!(b"ABC", &canonical.decode("QUJD").unwrap()[..]);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn check_decode_padding_forbidden() {
+        let unpadded = GeneralPurpose::new(
+            Config::new().with_decode_padding_mode(DecodePaddingMode::RequireNone),
+        );
+
+        assert_eq!(b"A", &unpadded.decode("QQ").unwrap()[..]);
+        assert_eq!(b"AB", &unpadded.decode("QUI").unwrap()[..]);
+        assert_eq!(Err(DecodeError::InvalidPadding(2)), unpadded.decode("QQ=="));
+        assert_eq!(Err(DecodeError::InvalidPadding(3)), unpadded.decode("QUI="));
+        assert_eq!(Err(DecodeError::InvalidPadding(4)), unpadded.decode("QUJD="));
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn check_decode_trailing_bits() {
+        let lenient = GeneralPurpose::new(Config::new().with_decode_allow_trailing_bits(true));
+
+        assert_eq!(Err(DecodeError::InvalidLastSymbol(1, b'R')), STANDARD.decode("QR=="));
+        assert_eq!(Err(DecodeError::InvalidLastSymbol(2, b'J')), STANDARD.decode("QUJ"));
+        assert_eq!(b"A", &lenient.decode("QR==").unwrap()[..]);
+        assert_eq!(b"AB", &lenient.decode("QUJ").unwrap()[..]);
+        assert_eq!(lenient.decode("QR=="), lenient.decode("QQ=="));
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn check_misplaced_padding() {
+        for &mode in &[
+            DecodePaddingMode::Indifferent,
+            DecodePaddingMode::RequireCanonical,
+            DecodePaddingMode::RequireNone,
+        ] {
+            let engine = GeneralPurpose::new(Config::new().with_decode_padding_mode(mode));
+
+            assert_eq!(Err(DecodeError::InvalidPadding(0)), engine.decode("=QUJD"));
+            assert_eq!(Err(DecodeError::InvalidPadding(2)), engine.decode("QQ==QUI="));
+            assert_eq!(Err(DecodeError::InvalidPadding(5)), engine.decode("QUJDR=I="));
+        }
+    }
+}
@@ -1,3 +1,44 @@
+#![no_std]
+
+//Slice based encoding and decoding only needs core.
+//The "alloc" feature adds the String and Vec APIs, and the "std" feature
+//(on by default) adds the std::io adapters and std::error::Error impls.
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+mod alphabet;
+#[cfg(all(feature = "std", target_arch = "x86_64"))]
+mod avx2;
+mod decode;
+mod display;
 mod encode;
+pub mod engine;
+#[cfg(feature = "std")]
+pub mod read;
+#[cfg(feature = "std")]
+pub mod write;
+mod table;
+#[cfg(all(test, feature = "alloc"))]
+mod test_util;
 
-pub use encode::{encode, encode_slice};
+pub use alphabet::{Alphabet, ParseAlphabetError};
+#[cfg(feature = "alloc")]
+pub use decode::decode;
+pub use decode::{
+    decode_array, decode_in_place, decode_slice, decode_to_slice, decoded_size,
+    exact_decoded_size, DecodeError, DecodeSliceError,
+};
+pub use display::{Base64Display, FmtEncoder};
+#[cfg(feature = "alloc")]
+pub use encode::{encode, encode_string, encode_vec};
+#[cfg(feature = "std")]
+pub use encode::encode_parallel;
+pub use encode::{
+    checked_encode_size, encode_array, encode_size, encode_slice, encode_to_slice,
+    EncodeSliceError,
+};
+pub use engine::Engine;
+pub use table::{encode_to_slice_double, DoubleSymbolTable};
@@ -0,0 +1,67 @@
+//Adapters for encoding and decoding from std::io::Read sources
+
+use std::io::{self, Read};
+
+mod decoder;
+mod encoder;
+
+pub use decoder::DecoderReader;
+pub use encoder::EncoderReader;
+
+//The delegate of a reader, with the input read from it that has not been
+//used yet.
+struct BufferedDelegate<R: Read, const N: usize> {
+    delegate: R,
+    input: [u8; N],
+    input_len: usize,
+    //delegate reached EOF
+    eof: bool,
+}
+
+impl<R: Read, const N: usize> BufferedDelegate<R, N> {
+    fn new(delegate: R) -> BufferedDelegate<R, N> {
+        BufferedDelegate {
+            delegate,
+            input: [0u8; N],
+            input_len: 0,
+            eof: false,
+        }
+    }
+
+    fn into_inner(self) -> R {
+        self.delegate
+    }
+
+    fn is_eof(&self) -> bool {
+        self.eof
+    }
+
+    fn is_full(&self) -> bool {
+        self.input_len == N
+    }
+
+    //the input read so far and not consumed yet
+    fn input(&self) -> &[u8] {
+        &self.input[..self.input_len]
+    }
+
+    //reads more input, unless the buffer is full or the delegate reached EOF
+    fn fill(&mut self) -> io::Result<()> {
+        if self.eof || self.is_full() {
+            return Ok(());
+        }
+
+        match self.delegate.read(&mut self.input[self.input_len..])? {
+            0 => self.eof = true,
+            read => self.input_len += read,
+        }
+
+        Ok(())
+    }
+
+    //drops the first len bytes of input(), which have been used
+    fn consume(&mut self, len: usize) {
+        self.input.copy_within(len..self.input_len, 0);
+        self.input_len -= len;
+    }
+}
@@ -0,0 +1,117 @@
+use std::io::{self, Read};
+
+use super::BufferedDelegate;
+use crate::decode::decode_buffered;
+use crate::engine::Engine;
+
+//size of the buffer holding symbols read from the delegate, a multiple of 4
+const BUF_SIZE: usize = 1024;
+const DECODED_BUF_SIZE: usize = BUF_SIZE / 4 * 3;
+
+//Reads Base64 from the delegate and yields the decoded bytes.
+//
+//Symbols are decoded in complete chunks of 4. The last chunk, with any
+//padding, is only decoded once the delegate reaches EOF, so that it is
+//checked against the engine's padding rules.
+//Decode errors are returned as io::ErrorKind::InvalidData wrapping the
+//DecodeError, whose offset counts from the start of the whole stream.
+pub struct DecoderReader<'e, E: Engine, R: Read> {
+    engine: &'e E,
+    //the delegate, with the symbols read from it but not decoded yet
+    delegate: BufferedDelegate<R, BUF_SIZE>,
+    //offset of the first symbol not decoded yet in the stream
+    input_offset: usize,
+    //decoded bytes not handed out yet
+    decoded: [u8; DECODED_BUF_SIZE],
+    decoded_start: usize,
+    decoded_len: usize,
+    //the final chunk has been decoded
+    finished: bool,
+}
+
+impl<'e, E: Engine, R: Read> DecoderReader<'e, E, R> {
+    pub fn new(delegate: R, engine: &'e E) -> DecoderReader<'e, E, R> {
+        DecoderReader {
+            engine,
+            delegate: BufferedDelegate::new(delegate),
+            input_offset: 0,
+            decoded: [0u8; DECODED_BUF_SIZE],
+            decoded_start: 0,
+            decoded_len: 0,
+            finished: false,
+        }
+    }
+
+    //Returns the delegate. Buffered input that was not decoded is lost.
+    pub fn into_inner(self) -> R {
+        self.delegate.into_inner()
+    }
+
+    //decodes every chunk that is known not to be the last one,
+    //or everything that is left once the delegate reached EOF
+    fn decode_input(&mut self) -> io::Result<()> {
+        let (decoded, written) = decode_buffered(
+            self.delegate.input(),
+            &mut self.decoded,
+            self.input_offset,
+            self.delegate.is_full(),
+            self.delegate.is_eof(),
+            self.engine,
+        )
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        self.delegate.consume(decoded);
+        self.input_offset += decoded;
+        self.decoded_start = 0;
+        self.decoded_len = written;
+        self.finished = self.delegate.is_eof();
+
+        Ok(())
+    }
+}
+
+impl<'e, E: Engine, R: Read> Read for DecoderReader<'e, E, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        while self.decoded_len == 0 {
+            if self.finished {
+                return Ok(0);
+            }
+
+            self.delegate.fill()?;
+            self.decode_input()?;
+        }
+
+        let len = self.decoded_len.min(buf.len());
+        buf[..len].copy_from_slice(&self.decoded[self.decoded_start..(self.decoded_start + len)]);
+        self.decoded_start += len;
+        self.decoded_len -= len;
+
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::DecodeError;
+    use crate::test_util::{check_stream_decode, decode_error, ShortReader};
+    use std::vec::Vec;
+
+    fn read_all<E: Engine>(encoded: &[u8], engine: &E) -> Result<Vec<u8>, DecodeError> {
+        let mut decoded = Vec::new();
+
+        DecoderReader::new(ShortReader::new(encoded), engine)
+            .read_to_end(&mut decoded)
+            .map(|_| decoded)
+            .map_err(decode_error)
+    }
+
+    #[test]
+    fn check_decode() {
+        check_stream_decode(read_all);
+    }
+}
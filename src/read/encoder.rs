@@ -0,0 +1,141 @@
+use std::io::{self, Read};
+
+use super::BufferedDelegate;
+use crate::encode::{encode_size, encode_with_padding};
+use crate::engine::Engine;
+
+//size of the buffer holding bytes read from the delegate, a multiple of 3
+const BUF_SIZE: usize = 768;
+const ENCODED_BUF_SIZE: usize = BUF_SIZE / 3 * 4;
+
+//Reads raw bytes from the delegate and yields their Base64 encoding.
+//
+//Complete chunks of 3 bytes are encoded as soon as they are read; the
+//last 1-2 bytes and the padding are encoded once the delegate reaches EOF.
+pub struct EncoderReader<'e, E: Engine, R: Read> {
+    engine: &'e E,
+    //the delegate, with the bytes read from it but not encoded yet
+    delegate: BufferedDelegate<R, BUF_SIZE>,
+    //encoded bytes not handed out yet
+    output: [u8; ENCODED_BUF_SIZE],
+    output_start: usize,
+    output_len: usize,
+    //the final chunk has been encoded
+    finished: bool,
+}
+
+impl<'e, E: Engine, R: Read> EncoderReader<'e, E, R> {
+    pub fn new(delegate: R, engine: &'e E) -> EncoderReader<'e, E, R> {
+        EncoderReader {
+            engine,
+            delegate: BufferedDelegate::new(delegate),
+            output: [0u8; ENCODED_BUF_SIZE],
+            output_start: 0,
+            output_len: 0,
+            finished: false,
+        }
+    }
+
+    //Returns the delegate. Buffered input that was not encoded is lost.
+    pub fn into_inner(self) -> R {
+        self.delegate.into_inner()
+    }
+
+    //encodes the complete chunks read so far,
+    //or everything that is left with padding once the delegate reached EOF
+    fn encode_input(&mut self) {
+        let eof = self.delegate.is_eof();
+        let input = self.delegate.input();
+        let input = if eof {
+            input
+        } else {
+            &input[..(input.len() / 3 * 3)]
+        };
+
+        self.output_len = if eof {
+            let encoded_size = encode_size(input.len(), self.engine.config().encode_padding());
+            encode_with_padding(input, &mut self.output[..encoded_size], encoded_size, self.engine);
+            encoded_size
+        } else {
+            self.engine.internal_encode(input, &mut self.output)
+        };
+
+        self.delegate.consume(input.len());
+        self.output_start = 0;
+        self.finished = eof;
+    }
+}
+
+impl<'e, E: Engine, R: Read> Read for EncoderReader<'e, E, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        while self.output_len == 0 {
+            if self.finished {
+                return Ok(0);
+            }
+
+            self.delegate.fill()?;
+            self.encode_input();
+        }
+
+        let len = self.output_len.min(buf.len());
+        buf[..len].copy_from_slice(&self.output[self.output_start..(self.output_start + len)]);
+        self.output_start += len;
+        self.output_len -= len;
+
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{STANDARD, URL_SAFE_NO_PAD};
+    use crate::read::DecoderReader;
+    use crate::test_util::{input, ShortReader};
+    use std::{string::String, vec::Vec};
+
+    #[test]
+    fn check_encode() {
+        for len in (0..3000).step_by(5).chain(3000..3010) {
+            let input = input(len);
+            let mut encoded = String::new();
+            EncoderReader::new(ShortReader::new(&input), &URL_SAFE_NO_PAD)
+                .read_to_string(&mut encoded)
+                .unwrap();
+
+            assert_eq!(URL_SAFE_NO_PAD.encode(&input), encoded);
+        }
+    }
+
+    #[test]
+    fn check_small_reads() {
+        let input = input(1000);
+        let mut encoder = EncoderReader::new(&input[..], &STANDARD);
+        let mut encoded = Vec::new();
+        let mut buf = [0u8; 3];
+
+        loop {
+            match encoder.read(&mut buf).unwrap() {
+                0 => break,
+                len => encoded.extend_from_slice(&buf[..len]),
+            }
+        }
+
+        assert_eq!(STANDARD.encode(&input).as_bytes(), &encoded[..]);
+    }
+
+    #[test]
+    fn check_round_trip_through_decoder() {
+        let input = input(5000);
+        let encoder = EncoderReader::new(&input[..], &STANDARD);
+        let mut decoded = Vec::new();
+
+        DecoderReader::new(encoder, &STANDARD).read_to_end(&mut decoded).unwrap();
+
+        assert_eq!(input, decoded);
+    }
+}
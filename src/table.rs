@@ -0,0 +1,148 @@
+use core::convert::TryInto;
+use core::fmt;
+
+use crate::alphabet::Alphabet;
+use crate::encode::encode_to_slice;
+
+//Maps every 12-bit value, half of a 3-byte chunk, to its two symbols, so that
+//a chunk takes 2 lookups instead of 4.
+//It takes 8 KiB, so build it once, e.g. in a static:
+//
+//static TABLE: DoubleSymbolTable = DoubleSymbolTable::new(&Alphabet::URL_SAFE);
+#[derive(Clone, PartialEq, Eq)]
+pub struct DoubleSymbolTable {
+    alphabet: Alphabet,
+    pairs: [[u8; 2]; 4096],
+}
+
+impl DoubleSymbolTable {
+    pub const fn new(alphabet: &Alphabet) -> DoubleSymbolTable {
+        let symbols = &alphabet.encode_table;
+        let mut pairs = [[0u8; 2]; 4096];
+
+        let mut i = 0;
+        while i < pairs.len() {
+            pairs[i] = [symbols[i >> 6], symbols[i & 0x3f]];
+            i += 1;
+        }
+
+        DoubleSymbolTable {
+            alphabet: *alphabet,
+            pairs,
+        }
+    }
+
+    pub const fn alphabet(&self) -> &Alphabet {
+        &self.alphabet
+    }
+}
+
+impl fmt::Debug for DoubleSymbolTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("DoubleSymbolTable").field(&self.alphabet).finish()
+    }
+}
+
+//encodes input to base64 bytes with the table's alphabet, like encode_to_slice()
+//but looking up two symbols at a time
+//output must be long enough to hold the encoded 'input' without padding
+//Returns the number of bytes written
+pub fn encode_to_slice_double(input: &[u8], output: &mut [u8], table: &DoubleSymbolTable) -> usize {
+    let pairs = &table.pairs;
+    let mut input_index: usize = 0;
+    let mut output_index: usize = 0;
+
+    // 12 bytes per iteration, read as two u64s whose last 2 bytes are ignored
+    while input.len() - input_index >= 14 && output.len() - output_index >= 16 {
+        let block: &[u8; 14] = input[input_index..(input_index + 14)].try_into().unwrap();
+        let out: &mut [u8; 16] = (&mut output[output_index..(output_index + 16)])
+            .try_into()
+            .unwrap();
+
+        for (half, start) in [0, 6].iter().enumerate() {
+            let word = u64::from_be_bytes(block[*start..(start + 8)].try_into().unwrap());
+
+            for i in 0..4 {
+                let pair = pairs[((word >> (52 - 12 * i)) & 0xfff) as usize];
+                out[half * 8 + i * 2] = pair[0];
+                out[half * 8 + i * 2 + 1] = pair[1];
+            }
+        }
+
+        input_index += 12;
+        output_index += 16;
+    }
+
+    output_index
+        + encode_to_slice(
+            &input[input_index..],
+            &mut output[output_index..],
+            &table.alphabet,
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "alloc")]
+    use crate::encode::{encode_size, encode_to_slice_runtime};
+    #[cfg(feature = "alloc")]
+    use crate::test_util::random_bytes;
+    #[cfg(feature = "alloc")]
+    use alloc::vec;
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn check_same_as_encode_to_slice() {
+        let crypt =
+            Alphabet::new("./0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz")
+                .unwrap();
+        let mut seed = 0x853c49e6748fea9b;
+
+        for alphabet in &[Alphabet::STANDARD, Alphabet::URL_SAFE, crypt] {
+            let table = DoubleSymbolTable::new(alphabet);
+
+            for len in (0..300).chain(4000..4003) {
+                let input = random_bytes(len, &mut seed);
+                let mut expected = vec![0u8; encode_size(len, false)];
+                let mut output = vec![0u8; encode_size(len, false)];
+
+                let expected_len = encode_to_slice(&input, &mut expected, alphabet);
+
+                assert_eq!(expected_len, encode_to_slice_double(&input, &mut output, &table));
+                assert_eq!(expected, output);
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn check_large_input_same_as_encode_to_slice() {
+        // not laid out like STANDARD, so the AVX2 encoder leaves all of it
+        let crypt =
+            Alphabet::new("./0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz")
+                .unwrap();
+        let mut seed = 0x94d049bb133111eb;
+        let input = random_bytes(100_000, &mut seed);
+
+        for alphabet in &[Alphabet::STANDARD, crypt] {
+            let mut expected = vec![0u8; encode_size(input.len(), false)];
+            let mut output = vec![0u8; encode_size(input.len(), false)];
+
+            encode_to_slice(&input, &mut expected, alphabet);
+            encode_to_slice_runtime(&input, &mut output, alphabet);
+
+            assert_eq!(expected, output);
+        }
+    }
+
+    #[test]
+    fn check_const_table() {
+        static TABLE: DoubleSymbolTable = DoubleSymbolTable::new(&Alphabet::STANDARD);
+        let mut output = [0u8; 8];
+
+        assert_eq!(7, encode_to_slice_double(b"ABCDE", &mut output, &TABLE));
+        assert_eq!(b"QUJDREU", &output[..7]);
+        assert_eq!(&Alphabet::STANDARD, TABLE.alphabet());
+    }
+}
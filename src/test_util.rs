@@ -0,0 +1,152 @@
+//Fixtures shared by the tests of several modules
+
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io::{self, Read, Write};
+#[cfg(feature = "std")]
+use std::{format, vec};
+
+#[cfg(feature = "std")]
+use crate::decode::DecodeError;
+#[cfg(feature = "std")]
+use crate::engine::{Config, DecodePaddingMode, Engine, GeneralPurpose, STANDARD};
+
+//len bytes of input that takes every byte value, with a pattern that doesn't
+//repeat every 256 bytes
+pub(crate) fn input(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i * 13 + i / 256) as u8).collect()
+}
+
+//len bytes from a xorshift64 generator, advancing seed
+pub(crate) fn random_bytes(len: usize, seed: &mut u64) -> Vec<u8> {
+    (0..len)
+        .map(|_| {
+            *seed ^= *seed << 13;
+            *seed ^= *seed >> 7;
+            *seed ^= *seed << 17;
+            *seed as u8
+        })
+        .collect()
+}
+
+//accepts at most a few bytes per call, and is interrupted now and then
+#[cfg(feature = "std")]
+pub(crate) struct ShortWriter {
+    pub(crate) written: Vec<u8>,
+    calls: usize,
+}
+
+#[cfg(feature = "std")]
+impl ShortWriter {
+    pub(crate) fn new() -> ShortWriter {
+        ShortWriter {
+            written: Vec::new(),
+            calls: 0,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Write for ShortWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.calls += 1;
+        if self.calls % 5 == 4 {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "interrupted"));
+        }
+
+        let len = buf.len().min(self.calls % 7 + 1);
+        self.written.extend_from_slice(&buf[..len]);
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+//returns reads of varying lengths, mostly short ones
+#[cfg(feature = "std")]
+pub(crate) struct ShortReader<'a> {
+    data: &'a [u8],
+    calls: usize,
+}
+
+#[cfg(feature = "std")]
+impl<'a> ShortReader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> ShortReader<'a> {
+        ShortReader { data, calls: 0 }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a> Read for ShortReader<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.calls += 1;
+
+        let max = [1, 2, 3, 5, 7, 11, 64, 1000][self.calls % 8];
+        let len = buf.len().min(self.data.len()).min(max);
+        buf[..len].copy_from_slice(&self.data[..len]);
+        self.data = &self.data[len..];
+        Ok(len)
+    }
+}
+
+//writes data in fragments of 1, 2, ... bytes
+#[cfg(feature = "std")]
+pub(crate) fn write_fragments<W: Write>(writer: &mut W, data: &[u8]) -> io::Result<()> {
+    let mut start = 0;
+    let mut fragment = 1;
+
+    while start < data.len() {
+        let end = (start + fragment).min(data.len());
+        writer.write_all(&data[start..end])?;
+        start = end;
+        fragment = fragment % 1500 + 1;
+    }
+
+    Ok(())
+}
+
+//the DecodeError of an error returned by a streaming decoder
+#[cfg(feature = "std")]
+pub(crate) fn decode_error(e: io::Error) -> DecodeError {
+    assert_eq!(io::ErrorKind::InvalidData, e.kind());
+    *e.into_inner().unwrap().downcast::<DecodeError>().unwrap()
+}
+
+//checks that decode_stream, which decodes its input through a streaming
+//decoder, returns what a decode of the whole input does
+#[cfg(feature = "std")]
+pub(crate) fn check_stream_decode<F>(decode_stream: F)
+where
+    F: Fn(&[u8], &GeneralPurpose) -> Result<Vec<u8>, DecodeError>,
+{
+    for len in (0..3000).step_by(7).chain(3000..3010) {
+        let input = input(len);
+        let encoded = STANDARD.encode(&input);
+
+        assert_eq!(Ok(input), decode_stream(encoded.as_bytes(), &STANDARD));
+    }
+
+    let encoded = STANDARD.encode(vec![0xa5u8; 2000]);
+
+    for &offset in &[0, 3, 1023, 1024, 1500, encoded.len() - 1] {
+        let mut invalid = encoded.clone().into_bytes();
+        invalid[offset] = b'*';
+
+        assert_eq!(
+            Err(DecodeError::InvalidByte(offset, b'*')),
+            decode_stream(&invalid, &STANDARD)
+        );
+    }
+
+    let canonical = GeneralPurpose::new(
+        Config::new().with_decode_padding_mode(DecodePaddingMode::RequireCanonical),
+    );
+
+    for suffix in &["QQ=A", "QQ==QUJD", "QQ===", "QR==", "QQ", "Q", "=QUJD", "QUJD\n"] {
+        let input = format!("{}{}", &encoded[..1200], suffix);
+
+        assert_eq!(canonical.decode(&input), decode_stream(input.as_bytes(), &canonical));
+    }
+}
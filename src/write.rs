@@ -0,0 +1,91 @@
+//Adapters for encoding and decoding into std::io::Write sinks
+
+use std::io::{self, Write};
+
+mod decoder;
+mod encoder;
+
+pub use decoder::DecoderWriter;
+pub use encoder::EncoderWriter;
+
+//The delegate of a writer, with the output it has not taken yet.
+//Output is kept until the delegate has taken all of it, so short writes from
+//the delegate are retried rather than lost.
+struct BufferedDelegate<W: Write, const N: usize> {
+    //None once finish() has returned it
+    delegate: Option<W>,
+    output: [u8; N],
+    output_len: usize,
+    //set while the delegate is writing, so that drop doesn't write after it panicked
+    panicked: bool,
+}
+
+impl<W: Write, const N: usize> BufferedDelegate<W, N> {
+    fn new(delegate: W) -> BufferedDelegate<W, N> {
+        BufferedDelegate {
+            delegate: Some(delegate),
+            output: [0u8; N],
+            output_len: 0,
+            panicked: false,
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.delegate.is_none()
+    }
+
+    //a writer dropped before finish() writes its remaining output, unless the
+    //delegate panicked
+    fn finish_on_drop(&self) -> bool {
+        !self.panicked && self.delegate.is_some()
+    }
+
+    fn take(&mut self) -> W {
+        self.delegate.take().expect("Writer has already finished")
+    }
+
+    //the part of the buffer that is free for more output
+    fn spare(&mut self) -> &mut [u8] {
+        &mut self.output[self.output_len..]
+    }
+
+    //marks len more bytes of spare() as output
+    fn add_output(&mut self, len: usize) {
+        self.output_len += len;
+    }
+
+    //writes the whole output buffer, retrying after short writes.
+    //on error, the bytes not yet written stay in the buffer.
+    fn write_output(&mut self) -> io::Result<()> {
+        let delegate = self.delegate.as_mut().expect("Writer must be present");
+
+        while self.output_len > 0 {
+            self.panicked = true;
+            let res = delegate.write(&self.output[..self.output_len]);
+            self.panicked = false;
+
+            match res {
+                Ok(0) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::WriteZero,
+                        "failed to write buffered output",
+                    ))
+                }
+                Ok(written) => {
+                    self.output.copy_within(written..self.output_len, 0);
+                    self.output_len -= written;
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(())
+    }
+
+    //writes the whole output buffer and flushes the delegate
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_output()?;
+        self.delegate.as_mut().expect("Writer must be present").flush()
+    }
+}
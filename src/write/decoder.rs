@@ -0,0 +1,158 @@
+use std::io::{self, Write};
+
+use super::BufferedDelegate;
+use crate::decode::decode_buffered;
+use crate::engine::Engine;
+
+//size of the buffer holding symbols that have not been decoded yet, a multiple of 4
+const BUF_SIZE: usize = 1024;
+const DECODED_BUF_SIZE: usize = BUF_SIZE / 4 * 3;
+
+//Decodes the Base64 written to it and writes the decoded bytes to the delegate.
+//
+//Symbols may be written in fragments of any size. Complete chunks are decoded
+//once the buffer fills up or on flush(); the last chunk, with any padding, is
+//held back until finish() checks it against the engine's padding rules.
+//Decode errors are returned as io::ErrorKind::InvalidData wrapping the
+//DecodeError, whose offset counts from the first byte written.
+pub struct DecoderWriter<'e, E: Engine, W: Write> {
+    engine: &'e E,
+    delegate: BufferedDelegate<W, DECODED_BUF_SIZE>,
+    //symbols that have not been decoded yet
+    input: [u8; BUF_SIZE],
+    input_len: usize,
+    //offset of input[0] in the stream
+    input_offset: usize,
+}
+
+impl<'e, E: Engine, W: Write> DecoderWriter<'e, E, W> {
+    pub fn new(delegate: W, engine: &'e E) -> DecoderWriter<'e, E, W> {
+        DecoderWriter {
+            engine,
+            delegate: BufferedDelegate::new(delegate),
+            input: [0u8; BUF_SIZE],
+            input_len: 0,
+            input_offset: 0,
+        }
+    }
+
+    //Decodes the last chunk, checking it and its padding, and writes all
+    //remaining output.
+    //Returns the delegate; writing afterwards panics.
+    pub fn finish(&mut self) -> io::Result<W> {
+        if self.delegate.is_finished() {
+            panic!("Decoder has already finished");
+        }
+
+        self.write_final()?;
+
+        Ok(self.delegate.take())
+    }
+
+    fn write_final(&mut self) -> io::Result<()> {
+        // all complete chunks first, so that the rest fits the output buffer
+        self.write_decoded(false)?;
+        self.write_decoded(true)
+    }
+
+    //decodes every chunk that is known not to be the last one, or everything
+    //that is left if last is set, and writes it to the delegate
+    fn write_decoded(&mut self, last: bool) -> io::Result<()> {
+        self.delegate.write_output()?;
+
+        let (decoded, written) = decode_buffered(
+            &self.input[..self.input_len],
+            self.delegate.spare(),
+            self.input_offset,
+            self.input_len == BUF_SIZE,
+            last,
+            self.engine,
+        )
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        self.input.copy_within(decoded..self.input_len, 0);
+        self.input_len -= decoded;
+        self.input_offset += decoded;
+        self.delegate.add_output(written);
+
+        self.delegate.write_output()
+    }
+}
+
+impl<'e, E: Engine, W: Write> Write for DecoderWriter<'e, E, W> {
+    //Buffers as much of input as fits.
+    //A full buffer is decoded and written to the delegate first, so an error
+    //is returned only when none of input was consumed.
+    fn write(&mut self, input: &[u8]) -> io::Result<usize> {
+        if self.delegate.is_finished() {
+            panic!("Cannot write more after calling finish()");
+        }
+        if input.is_empty() {
+            return Ok(0);
+        }
+
+        if self.input_len == BUF_SIZE {
+            self.write_decoded(false)?;
+        }
+
+        let take = (BUF_SIZE - self.input_len).min(input.len());
+        self.input[self.input_len..(self.input_len + take)].copy_from_slice(&input[..take]);
+        self.input_len += take;
+
+        Ok(take)
+    }
+
+    //Decodes all complete chunks written so far, writes them to the delegate
+    //and flushes it. The last chunk stays buffered until finish().
+    fn flush(&mut self) -> io::Result<()> {
+        if self.delegate.is_finished() {
+            panic!("Cannot write more after calling finish()");
+        }
+
+        self.write_decoded(false)?;
+        self.delegate.flush()
+    }
+}
+
+impl<'e, E: Engine, W: Write> Drop for DecoderWriter<'e, E, W> {
+    fn drop(&mut self) {
+        if self.delegate.finish_on_drop() {
+            let _ = self.write_final();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::DecodeError;
+    use crate::engine::STANDARD;
+    use crate::test_util::{check_stream_decode, decode_error, write_fragments, ShortWriter};
+    use std::vec::Vec;
+
+    fn write_all<E: Engine>(encoded: &[u8], engine: &E) -> Result<Vec<u8>, DecodeError> {
+        let mut decoder = DecoderWriter::new(ShortWriter::new(), engine);
+
+        write_fragments(&mut decoder, encoded)
+            .and_then(|_| decoder.finish())
+            .map(|delegate| delegate.written)
+            .map_err(decode_error)
+    }
+
+    #[test]
+    fn check_decode() {
+        check_stream_decode(write_all);
+    }
+
+    #[test]
+    fn check_finish_on_drop() {
+        let mut output = Vec::new();
+        {
+            let mut decoder = DecoderWriter::new(&mut output, &STANDARD);
+            decoder.write_all(b"QUJD").unwrap();
+            decoder.write_all(b"REU=").unwrap();
+        }
+
+        assert_eq!(b"ABCDE", &output[..]);
+    }
+}
@@ -0,0 +1,183 @@
+use std::io::{self, Write};
+
+use super::BufferedDelegate;
+use crate::encode::add_padding;
+use crate::engine::Engine;
+
+//size of the buffer holding encoded output until the delegate accepts it
+const BUF_SIZE: usize = 1024;
+
+//Encodes everything written to it as Base64 and writes that to the delegate.
+//
+//Up to 2 input bytes that do not form a complete chunk are kept between
+//writes, and encoded output is buffered until the delegate has taken all of it.
+//The final chunk and padding are written by finish(), or on drop if
+//finish() was never called; call finish() to see any error.
+pub struct EncoderWriter<'e, E: Engine, W: Write> {
+    engine: &'e E,
+    delegate: BufferedDelegate<W, BUF_SIZE>,
+    //input bytes left over from the previous write, less than a chunk
+    extra_input: [u8; 3],
+    extra_input_len: usize,
+}
+
+impl<'e, E: Engine, W: Write> EncoderWriter<'e, E, W> {
+    pub fn new(delegate: W, engine: &'e E) -> EncoderWriter<'e, E, W> {
+        EncoderWriter {
+            engine,
+            delegate: BufferedDelegate::new(delegate),
+            extra_input: [0u8; 3],
+            extra_input_len: 0,
+        }
+    }
+
+    //Encodes the leftover input with padding and writes all remaining output.
+    //Returns the delegate; writing afterwards panics.
+    pub fn finish(&mut self) -> io::Result<W> {
+        if self.delegate.is_finished() {
+            panic!("Encoder has already finished");
+        }
+
+        self.write_final()?;
+
+        Ok(self.delegate.take())
+    }
+
+    fn write_final(&mut self) -> io::Result<()> {
+        if self.extra_input_len > 0 {
+            // make room for at most 3 symbols and 1 pad
+            if self.delegate.spare().len() < 4 {
+                self.delegate.write_output()?;
+            }
+
+            let extra_input = &self.extra_input[..self.extra_input_len];
+            let output = self.delegate.spare();
+
+            let mut encoded_len = self.engine.internal_encode(extra_input, output);
+            if self.engine.config().encode_padding() {
+                encoded_len += add_padding(extra_input.len(), &mut output[encoded_len..]);
+            }
+
+            self.delegate.add_output(encoded_len);
+            self.extra_input_len = 0;
+        }
+
+        self.delegate.write_output()
+    }
+}
+
+impl<'e, E: Engine, W: Write> Write for EncoderWriter<'e, E, W> {
+    //Encodes as much of input as fits in the output buffer.
+    //The buffer is only written to the delegate once it is full, so an error
+    //is returned only when none of input was consumed.
+    fn write(&mut self, input: &[u8]) -> io::Result<usize> {
+        if self.delegate.is_finished() {
+            panic!("Cannot write more after calling finish()");
+        }
+        if input.is_empty() {
+            return Ok(0);
+        }
+
+        // make room for at least one chunk
+        if self.delegate.spare().len() < 4 {
+            self.delegate.write_output()?;
+        }
+
+        let mut consumed = 0;
+
+        if self.extra_input_len > 0 {
+            let take = (3 - self.extra_input_len).min(input.len());
+            self.extra_input[self.extra_input_len..(self.extra_input_len + take)]
+                .copy_from_slice(&input[..take]);
+            self.extra_input_len += take;
+            consumed += take;
+
+            if self.extra_input_len < 3 {
+                return Ok(consumed);
+            }
+
+            let encoded_len = self
+                .engine
+                .internal_encode(&self.extra_input, self.delegate.spare());
+            self.delegate.add_output(encoded_len);
+            self.extra_input_len = 0;
+        }
+
+        let room_chunks = self.delegate.spare().len() / 4;
+        let complete_chunks = ((input.len() - consumed) / 3).min(room_chunks);
+        let chunks_input = &input[consumed..(consumed + complete_chunks * 3)];
+
+        let encoded_len = self.engine.internal_encode(chunks_input, self.delegate.spare());
+        self.delegate.add_output(encoded_len);
+        consumed += chunks_input.len();
+
+        // all complete chunks fit, keep the 0-2 bytes left for the next write
+        if complete_chunks < room_chunks {
+            let rem = input.len() - consumed;
+            self.extra_input[..rem].copy_from_slice(&input[consumed..]);
+            self.extra_input_len = rem;
+            consumed += rem;
+        }
+
+        Ok(consumed)
+    }
+
+    //Writes all complete chunks encoded so far to the delegate and flushes it.
+    //Leftover input that is not a complete chunk stays buffered until finish().
+    fn flush(&mut self) -> io::Result<()> {
+        if self.delegate.is_finished() {
+            panic!("Cannot write more after calling finish()");
+        }
+
+        self.delegate.flush()
+    }
+}
+
+impl<'e, E: Engine, W: Write> Drop for EncoderWriter<'e, E, W> {
+    fn drop(&mut self) {
+        if self.delegate.finish_on_drop() {
+            // like BufWriter, errors on drop are ignored; use finish() to see them
+            let _ = self.write_final();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{STANDARD, URL_SAFE_NO_PAD};
+    use crate::test_util::{input, write_fragments, ShortWriter};
+    use std::vec::Vec;
+
+    #[test]
+    fn check_fragmented_writes() {
+        for len in 0..3000 {
+            let input = input(len);
+            let mut encoder = EncoderWriter::new(Vec::new(), &STANDARD);
+            write_fragments(&mut encoder, &input).unwrap();
+
+            assert_eq!(STANDARD.encode(&input).as_bytes(), &encoder.finish().unwrap()[..]);
+        }
+    }
+
+    #[test]
+    fn check_short_writes() {
+        let input = input(5000);
+        let mut encoder = EncoderWriter::new(ShortWriter::new(), &URL_SAFE_NO_PAD);
+        encoder.write_all(&input).unwrap();
+        let delegate = encoder.finish().unwrap();
+
+        assert_eq!(URL_SAFE_NO_PAD.encode(&input).as_bytes(), &delegate.written[..]);
+    }
+
+    #[test]
+    fn check_finish_on_drop() {
+        let mut output = Vec::new();
+        {
+            let mut encoder = EncoderWriter::new(&mut output, &STANDARD);
+            encoder.write_all(b"ABCDE").unwrap();
+        }
+
+        assert_eq!(b"QUJDREU=", &output[..]);
+    }
+}